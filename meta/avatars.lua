@@ -0,0 +1,19 @@
+---@meta
+
+---@class avatarslib
+local avatars = {}
+
+--- Every allowed avatar, nil if the bureau allows any `avtwrl/` avatar.
+---@return string[]?
+function avatars.list() end
+
+--- Check if users are allowed to pick avatar.
+---@param avatar string
+---@return boolean
+function avatars.allowed(avatar) end
+
+--- Avatar given to users that pick one that isn't allowed.
+---@return string
+function avatars.default() end
+
+return avatars
@@ -0,0 +1,63 @@
+---@meta
+
+---@class bureaunames
+---@field min_length integer
+---@field max_length integer
+---@field charset "printable"|"ascii"|"alphanumeric"
+---@field unique boolean
+---@field reserved string[]
+
+---@class bureaubounds
+---@field min number[] Corner with the smallest x, y and z.
+---@field max number[]
+
+---@class bureausandbox
+---@field instruction_limit integer
+---@field memory_limit integer Bytes.
+
+--- Live view of the bureau's config, only the aura settings, update rate, `max_speed`, `max_users`, `motd` and `count_bots` can be set.
+---@class bureauconfig
+---@field connect_timeout integer
+---@field max_users integer
+---@field max_queue integer
+---@field aura_radius number
+---@field aura_leave_radius number? Distance users have to move away before leaving an aura, `aura_radius` when nil.
+---@field aura_dwell integer Milliseconds a pair of users stays in or out of each other's aura before it can change.
+---@field max_aura_changes integer Max aura changes per tick, 0 for no limit.
+---@field max_update_rate number Max movement updates per second forwarded from one user to another, 0 for no limit.
+---@field update_falloff number Users further away than this get movement updates less often, 0 to disable.
+---@field plugins string[]?
+---@field sandbox bureausandbox?
+---@field motd string?
+---@field avatars string?
+---@field default_avatar string
+---@field names bureaunames
+---@field world_bounds bureaubounds? Box users have to stay inside of, anywhere is allowed when nil.
+---@field max_speed number? Max distance per second users can move between updates, no limit when nil.
+---@field count_bots boolean
+---@field storage string
+---@field wrl string?
+
+---@class bureaulib
+---@field port integer Port the bureau is listening on.
+---@field started_at number Unix time the bureau started at, in seconds.
+---@field config bureauconfig
+local bureau = {}
+
+--- Seconds since the bureau started.
+---@return number
+function bureau.uptime() end
+
+--- Number of users, the same count clients are sent.
+---@return integer
+function bureau.user_count() end
+
+--- Get the current master, nil if there is none.
+---@return User?
+function bureau.master() end
+
+--- Check if the bureau was started by a WLS.
+---@return boolean
+function bureau.is_wls() end
+
+return bureau
@@ -23,22 +23,51 @@ function hook.position_update(fn) end
 ---@return integer
 function hook.transform_update(fn) end
 
+--- Called when a movement update breaks the movement rules, the update is dropped unless a hook
+--- returns true. pos is where the user tried to move to and reason is "invalid", "out_of_bounds"
+--- or "too_fast". Invalid updates are always dropped.
+---@param fn fun(user: User, pos: Vector, reason: string):boolean?
+---@return integer
+function hook.movement_violation(fn) end
+
 ---@param fn fun(user: User, msg: string):string?
 ---@return integer
 function hook.chat_send(fn) end
 
----@param fn fun(user: User, name: string, old: string)
+--- Called with every name a user picks when joining or changing their name. suggested is the
+--- name the rules give them and reason is nil if name follows the rules, otherwise "invalid",
+--- "reserved" or "taken". Return a string to use that name instead, or an empty string to reject
+--- it. `users[id]` is nil while the user is joining.
+---@param fn fun(id: integer, ip: string, name: string, suggested: string, reason: string?):string?
+---@return integer
+function hook.validate_name(fn) end
+
+--- Return a string to replace the new name, or an empty string to reject the change.
+---@param fn fun(user: User, name: string, old: string):string?
 ---@return integer
 function hook.name_change(fn) end
 
----@param fn fun(user: User, avatar: string, old: string)
+--- Return a string to replace the new avatar, or an empty string to reject the change.
+---@param fn fun(user: User, avatar: string, old: string):string?
 ---@return integer
 function hook.avatar_change(fn) end
 
+---@param fn fun(user: User, data: string)
+---@return integer
+function hook.character_update(fn) end
+
 ---@param fn fun(sender: User, receiver: User, msg: string):string?
 ---@return integer
 function hook.private_chat(fn) end
 
+--- Called with every packet a user sends before it's decoded, user is nil until they've joined.
+--- section_type is 0 for general messages, 1 for the unknown 14 byte section and 2 for position
+--- updates, bytes is everything after it. Return a string to decode instead, or an empty string
+--- to drop the packet. Packets are only passed to Lua while a raw_packet hook is registered.
+---@param fn fun(user: User?, section_type: integer, bytes: string):string?
+---@return integer
+function hook.raw_packet(fn) end
+
 ---@param fn fun(u1: User, u2: User)
 ---@return integer
 function hook.aura_enter(fn) end
@@ -55,4 +84,9 @@ function hook.user_disconnect(fn) end
 ---@return integer
 function hook.plugins_loaded(fn) end
 
+--- Remove a hook, returns false if the handle wasn't valid.
+---@param handle integer Handle returned when adding the hook.
+---@return boolean
+function hook.remove(handle) end
+
 return hook
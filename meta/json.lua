@@ -0,0 +1,22 @@
+---@meta
+
+---@class jsonlib
+local json = {}
+
+--- Encode a value as JSON, errors if it contains functions, userdata or threads.
+---@param value any
+---@param pretty boolean? Indent the output.
+---@return string
+function json.encode(value, pretty) end
+
+--- Decode JSON, nulls are decoded as nil.
+---@param s string
+---@return any
+function json.decode(s) end
+
+--- Mark a table as an array so it's encoded as `[]` even when it's empty.
+---@param tbl table?
+---@return table
+function json.array(tbl) end
+
+return json
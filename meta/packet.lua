@@ -0,0 +1,95 @@
+---@meta
+
+--- Builds big endian VSCP packets, every write returns the ByteWriter so they can be chained.
+--- Writes error once a ByteWriter would hold more than 1041 bytes.
+---@class ByteWriter
+local ByteWriter = {}
+
+---@param n integer
+---@return ByteWriter
+function ByteWriter:write_u8(n) end
+
+---@param n integer
+---@return ByteWriter
+function ByteWriter:write_i32(n) end
+
+---@param n integer
+---@return ByteWriter
+function ByteWriter:write_u32(n) end
+
+--- Write a number as fixed point, the way positions and rotations are sent. 1.0 is written as
+--- 65535, numbers are rounded to the nearest step and ones too large to fit are clamped.
+---@param n number
+---@return ByteWriter
+function ByteWriter:write_f32(n) end
+
+--- Write a null terminated string.
+---@param s string
+---@return ByteWriter
+function ByteWriter:write_string(s) end
+
+--- Write raw bytes.
+---@param s string
+---@return ByteWriter
+function ByteWriter:write_bytes(s) end
+
+--- Get everything written so far.
+---@return string
+function ByteWriter:bytes() end
+
+---@class packetlib
+local packet = {}
+
+---@enum Opcode
+packet.Opcode = {
+	SMsgClientId = 1,
+	SMsgUserJoined = 2,
+	SMsgUserLeft = 3,
+	SMsgBroadcastId = 4,
+	MsgCommon = 6,
+	SMsgSetMaster = 8,
+	SMsgUserCount = 11,
+}
+
+---@enum MsgCommon
+packet.MsgCommon = {
+	TransformUpdate = 2,
+	ChatSend = 9,
+	CharacterUpdate = 12,
+	NameChange = 13,
+	AvatarChange = 14,
+	PrivateChat = 15,
+	ApplSpecific = 10000,
+}
+
+---@enum Strategy
+packet.Strategy = {
+	AuraClients = 0,
+	AuraClientsExceptSender = 1,
+	SpecificClient = 2,
+	AllClients = 3,
+	AllClientsExceptSender = 4,
+}
+
+--- Create an empty ByteWriter.
+---@return ByteWriter
+function packet.writer() end
+
+--- Build a general message packet.
+---@param id1 integer
+---@param id2 integer
+---@param opcode Opcode|integer
+---@param content (ByteWriter|string)?
+---@return ByteWriter
+function packet.general_message(id1, id2, opcode, content) end
+
+--- Build a MsgCommon packet.
+---@param id1 integer
+---@param id2 integer
+---@param msg_type MsgCommon|integer
+---@param strategy Strategy|integer
+---@param content (ByteWriter|string)?
+---@return ByteWriter
+function packet.message_common(id1, id2, msg_type, strategy, content) end
+
+return packet
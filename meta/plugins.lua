@@ -0,0 +1,27 @@
+---@meta
+
+---@class pluginslib
+local plugins = {}
+
+--- Get the names of all loaded plugins.
+---@return string[]
+function plugins.all() end
+
+--- Get the name of the plugin that is currently running.
+---@return string?
+function plugins.current() end
+
+--- Check if a plugin's hooks are being run.
+---@param name string
+---@return boolean
+function plugins.enabled(name) end
+
+--- Stop running hooks from a plugin.
+---@param name string
+function plugins.disable(name) end
+
+--- Start running hooks from a disabled plugin again.
+---@param name string
+function plugins.enable(name) end
+
+return plugins
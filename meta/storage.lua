@@ -0,0 +1,24 @@
+---@meta
+
+---@class storagelib
+local storage = {}
+
+--- Get a value saved by this plugin.
+---@param key string
+---@return any
+function storage.get(key) end
+
+--- Save a value for this plugin, it can be nil, a boolean, number, string, or a table of them.
+---@param key string
+---@param value any
+function storage.set(key, value) end
+
+--- Remove a value saved by this plugin.
+---@param key string
+function storage.remove(key) end
+
+--- Get every key this plugin has saved.
+---@return string[]
+function storage.keys() end
+
+return storage
@@ -0,0 +1,28 @@
+---@meta
+
+---@class tasklib
+local task = {}
+
+--- Run fn as a coroutine, starting on the next think. Extra arguments are passed to fn.
+---@param fn fun(...)
+---@return integer
+function task.spawn(fn, ...) end
+
+--- Pause the current task for sec seconds.
+---@param sec number
+function task.sleep(sec) end
+
+--- Pause the current task until event is notified, returns the values it was notified with.
+--- Every hook is also an event, `task.wait_for("chat_send")` returns the same values a chat_send hook gets.
+--- Returns nothing if timeout seconds pass first.
+---@param event string
+---@param timeout number?
+---@return ...
+function task.wait_for(event, timeout) end
+
+--- Wake every task waiting for event.
+---@param event string
+---@param ... any
+function task.notify(event, ...) end
+
+return task
@@ -0,0 +1,27 @@
+---@meta
+
+---@class timerlib
+local timer = {}
+
+--- Call fn once after sec seconds.
+---@param sec number
+---@param fn fun()
+---@return integer
+function timer.after(sec, fn) end
+
+--- Call fn every sec seconds.
+---@param sec number
+---@param fn fun()
+---@return integer
+function timer.every(sec, fn) end
+
+--- Stop a timer, returns false if the id wasn't valid.
+---@param id integer
+---@return boolean
+function timer.cancel(id) end
+
+--- Seconds since the Lua state was created, never goes backwards.
+---@return number
+function timer.now() end
+
+return timer
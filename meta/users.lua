@@ -32,14 +32,93 @@ function user_meta:set_rot(rot) end
 ---@return Basis
 function user_meta:rot() end
 
---- Send a message to the User's chat.
+--- Send a message to the User's chat, as from if set or as a system message if not.
 ---@param msg string
-function user_meta:send_msg(msg) end
+---@param from User?
+function user_meta:send_msg(msg, from) end
 
---- Send a packet to the User.
+--- Send a message to the chat of everyone in User's aura, as from if set or as a system message if not.
 ---@param msg string
+---@param from User?
+function user_meta:send_aura_msg(msg, from) end
+
+--- Send a packet to the User.
+---@param msg string|ByteWriter
 function user_meta:send_packet(msg) end
 
+--- Change User's name for them and everyone in their aura, runs name_change hooks.
+--- Name rules aren't applied, so plugins can give out reserved names.
+--- Returns false if a hook rejected the change.
+---@param name string
+---@return boolean
+function user_meta:set_name(name) end
+
+--- Change User's avatar for them and everyone in their aura, runs avatar_change hooks.
+--- Returns false if a hook rejected the change.
+---@param avatar string
+---@return boolean
+function user_meta:set_avatar(avatar) end
+
+--- Change User's character data for them and everyone in their aura, runs character_update hooks.
+---@param data string
+function user_meta:set_character_data(data) end
+
+--- Get the Users in this User's aura.
+---@return User[]
+function user_meta:aura() end
+
+--- Get User's character data, see CharacterUpdate in VSCP.md.
+---@return string
+function user_meta:character_data() end
+
+--- Get the time the User connected as a unix timestamp.
+---@return number
+function user_meta:connected_at() end
+
+--- Check if the User is the master client.
+---@return boolean
+function user_meta:is_master() end
+
+--- Get User's ip and port, nil for bots.
+---@return string?
+function user_meta:addr() end
+
+--- Check if the User has finished joining.
+---@return boolean
+function user_meta:initialized() end
+
+--- Check if the User is a bot spawned by a plugin.
+---@return boolean
+function user_meta:is_bot() end
+
+--- Get the User's own aura radius, nil if it uses `bureau.config.aura_radius`.
+---@return number?
+function user_meta:aura_radius() end
+
+--- Give the User their own aura radius, nil to use `bureau.config.aura_radius` again.
+--- Two users are in each other's aura when they're within the larger of their radii.
+---@param radius number?
+function user_meta:set_aura_radius(radius) end
+
+--- Check if the User is hidden.
+---@return boolean
+function user_meta:hidden() end
+
+--- Hide the User from every aura, they won't see other users either.
+---@param hidden boolean
+function user_meta:set_hidden(hidden) end
+
+--- Get the aura override for this User and other.
+---@param other User
+---@return boolean?
+function user_meta:aura_override(other) end
+
+--- Force the User and other in (true) or out (false) of each other's aura, nil removes the override.
+--- Overrides take priority over radii and hidden users.
+---@param other User
+---@param include boolean?
+function user_meta:set_aura_override(other, include) end
+
 function user_meta:__tostring() end
 
 ---@class userslib
@@ -54,4 +133,17 @@ function user_manager.all() end
 ---@return User
 function user_manager.get(id) end
 
+--- Send a message to everyone's chat, as from if set or as a system message if not.
+---@param msg string
+---@param from User?
+function user_manager.broadcast(msg, from) end
+
+--- Spawn a bot, a user without a connection that plugins control. Runs new_user hooks.
+--- Returns nil if the bureau is full.
+---@param name string
+---@param avatar string? Defaults to the bureau's default avatar.
+---@param data string? Character data.
+---@return User?
+function user_manager.spawn_bot(name, avatar, data) end
+
 return user_manager
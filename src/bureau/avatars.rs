@@ -0,0 +1,112 @@
+use std::{
+	fs,
+	io::{self, BufRead, BufReader},
+	path::Path,
+};
+
+use hashbrown::HashSet;
+
+/// Prefix every avatar path has to start with.
+const PREFIX: &str = "avtwrl/";
+
+/// Avatars users are allowed to pick.
+pub struct AvatarCatalog {
+	/// Allowed avatars, any well formed `avtwrl/` path is allowed when `None`.
+	avatars: Option<HashSet<String>>,
+	default: String,
+}
+
+impl AvatarCatalog {
+	/// Create a catalog that allows any well formed `avtwrl/` path.
+	pub fn new(default: String) -> Self {
+		Self {
+			avatars: None,
+			default,
+		}
+	}
+
+	/// Load a catalog from `path`.
+	///
+	/// A directory is scanned and every file in it becomes `avtwrl/<file name>`. Anything else is
+	/// read as a list with one avatar per line, empty lines and lines starting with `#` are
+	/// skipped.
+	pub fn load(path: &Path, default: String) -> io::Result<Self> {
+		let mut avatars = HashSet::new();
+
+		if path.is_dir() {
+			for file in fs::read_dir(path)? {
+				let file = file?;
+				if file.file_type()?.is_file() {
+					avatars.insert(format!("{}{}", PREFIX, file.file_name().to_string_lossy()));
+				}
+			}
+		} else {
+			for line in BufReader::new(fs::File::open(path)?).lines() {
+				let line = line?;
+				let line = line.trim();
+
+				if line.is_empty() || line.starts_with('#') {
+					continue;
+				}
+
+				if !well_formed(line) {
+					eprintln!("{:?}: skipping invalid avatar '{}'", path, line);
+					continue;
+				}
+
+				avatars.insert(line.to_string());
+			}
+		}
+
+		if !avatars.contains(&default) {
+			eprintln!(
+				"{:?}: default avatar '{}' isn't in the avatar catalog",
+				path, default
+			);
+		}
+
+		Ok(Self {
+			avatars: Some(avatars),
+			default,
+		})
+	}
+
+	/// Avatar given to users that pick one that isn't allowed.
+	pub fn default(&self) -> &String {
+		&self.default
+	}
+
+	/// Allowed avatars, `None` if any well formed `avtwrl/` path is allowed.
+	pub fn avatars(&self) -> Option<&HashSet<String>> {
+		self.avatars.as_ref()
+	}
+
+	pub fn is_allowed(&self, avatar: &str) -> bool {
+		match &self.avatars {
+			Some(avatars) => avatars.contains(avatar),
+			None => well_formed(avatar),
+		}
+	}
+
+	/// Get `avatar` if it's allowed, otherwise the default avatar.
+	pub fn validate(&self, avatar: String) -> String {
+		if self.is_allowed(&avatar) {
+			return avatar;
+		}
+
+		self.default.clone()
+	}
+}
+
+/// Check that an avatar is a relative path inside of `avtwrl/`.
+fn well_formed(avatar: &str) -> bool {
+	let Some(path) = avatar.strip_prefix(PREFIX) else {
+		return false;
+	};
+
+	!path.is_empty()
+		&& !path.contains(|c: char| c == '\\' || c == ':' || c.is_control())
+		&& path
+			.split('/')
+			.all(|part| !part.is_empty() && part != "." && part != "..")
+}
@@ -0,0 +1,181 @@
+use std::time::{Duration, Instant};
+
+use hashbrown::HashMap;
+
+use super::user::User;
+
+/// Kinds of movement packets, only the latest packet of each kind is forwarded.
+#[derive(Clone, Copy)]
+pub enum Movement {
+	Position = 0,
+	Transform = 1,
+}
+
+/// How often movement is forwarded, set from the bureau's config.
+#[derive(Clone, Copy, PartialEq)]
+pub struct UpdateRate {
+	/// Max movement updates per second sent from one user to another, no limit when 0.
+	pub max_rate: f32,
+	/// Users further away than this get updates less often, at `max_rate * falloff / distance`.
+	/// Off when 0.
+	pub falloff: f32,
+}
+
+impl UpdateRate {
+	pub fn is_limited(&self) -> bool {
+		self.max_rate > 0.0
+	}
+
+	/// Min time between updates sent to a user `distance` away.
+	fn interval(&self, distance: f32) -> Duration {
+		if !self.is_limited() {
+			return Duration::ZERO;
+		}
+
+		let mut rate = self.max_rate;
+		if self.falloff > 0.0 && distance > self.falloff {
+			rate *= self.falloff / distance;
+		}
+
+		Duration::try_from_secs_f32(1.0 / rate).unwrap_or(Duration::MAX)
+	}
+}
+
+#[derive(Default)]
+struct Sender {
+	/// Latest packet of each `Movement` kind and the order it arrived in.
+	latest: Latest,
+	next_seq: u64,
+	/// When each receiver was last sent an update.
+	sent_at: HashMap<i32, Instant>,
+	/// Kinds of packets each receiver hasn't been sent the latest of.
+	pending: HashMap<i32, [bool; 2]>,
+}
+
+impl Sender {
+	fn set_latest(&mut self, kind: Movement, bytes: Vec<u8>) {
+		self.latest[kind as usize] = Some((self.next_seq, bytes));
+		self.next_seq += 1;
+	}
+}
+
+type Latest = [Option<(u64, Vec<u8>)>; 2];
+
+/// Latest packets of the `kinds` that are pending, in the order they arrived.
+///
+/// Transforms carry a position too, so sending an older transform after a newer position would
+/// leave receivers at the old position.
+fn due<'a>(latest: &'a Latest, kinds: &[bool; 2]) -> Vec<&'a [u8]> {
+	let mut due = kinds
+		.iter()
+		.zip(latest)
+		.filter_map(|(pending, latest)| latest.as_ref().filter(|_| *pending))
+		.collect::<Vec<_>>();
+	due.sort_unstable_by_key(|(seq, _)| *seq);
+
+	due.into_iter().map(|(_, bytes)| bytes.as_slice()).collect()
+}
+
+/// Movement packets waiting to be forwarded to aura members, kept per sender.
+#[derive(Default)]
+pub struct Fanout {
+	senders: HashMap<i32, Sender>,
+}
+
+impl Fanout {
+	/// Replace the latest packet of `kind` from `sender`, every aura member is due to get it.
+	pub fn push(&mut self, sender: &User, kind: Movement, bytes: Vec<u8>) {
+		let entry = self.senders.entry(sender.id()).or_default();
+		entry.set_latest(kind, bytes);
+
+		for receiver in sender.aura() {
+			entry.pending.entry(*receiver).or_default()[kind as usize] = true;
+		}
+	}
+
+	/// Send pending packets from `sender` to every receiver whose interval has passed.
+	pub fn flush(&mut self, sender: &User, users: &mut HashMap<i32, User>, rate: &UpdateRate) {
+		let Some(entry) = self.senders.get_mut(&sender.id()) else {
+			return;
+		};
+
+		let now = Instant::now();
+
+		entry.pending.retain(|receiver, kinds| {
+			if !sender.aura().contains(receiver) {
+				return false;
+			}
+
+			let Some(other) = users.get_mut(receiver) else {
+				return false;
+			};
+
+			let interval = rate.interval(sender.pos().distance(other.pos()));
+			if entry
+				.sent_at
+				.get(receiver)
+				.is_some_and(|at| now.duration_since(*at) < interval)
+			{
+				return true;
+			}
+
+			for bytes in due(&entry.latest, kinds) {
+				other.send(bytes);
+			}
+			entry.sent_at.insert(*receiver, now);
+
+			false
+		});
+	}
+
+	/// Users with packets that haven't been sent to everyone yet.
+	pub fn pending_senders(&self) -> Vec<i32> {
+		self.senders
+			.iter()
+			.filter(|(_, sender)| !sender.pending.is_empty())
+			.map(|(id, _)| *id)
+			.collect()
+	}
+
+	/// Forget everything sent from and to `id`.
+	pub fn remove(&mut self, id: i32) {
+		self.senders.remove(&id);
+
+		for sender in self.senders.values_mut() {
+			sender.sent_at.remove(&id);
+			sender.pending.remove(&id);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn due_packets_keep_arrival_order() {
+		let mut sender = Sender::default();
+		sender.set_latest(Movement::Transform, vec![1]);
+		sender.set_latest(Movement::Position, vec![2]);
+		assert_eq!(due(&sender.latest, &[true, true]), vec![&[1][..], &[2]]);
+
+		// A newer transform replaces the old one and goes after the position.
+		sender.set_latest(Movement::Transform, vec![3]);
+		assert_eq!(due(&sender.latest, &[true, true]), vec![&[2][..], &[3]]);
+
+		sender.set_latest(Movement::Position, vec![4]);
+		assert_eq!(due(&sender.latest, &[true, true]), vec![&[3][..], &[4]]);
+	}
+
+	#[test]
+	fn only_pending_kinds_are_due() {
+		let mut sender = Sender::default();
+		assert!(due(&sender.latest, &[true, true]).is_empty());
+
+		sender.set_latest(Movement::Position, vec![1]);
+		sender.set_latest(Movement::Transform, vec![2]);
+		assert_eq!(due(&sender.latest, &[true, false]), vec![&[1][..]]);
+		assert_eq!(due(&sender.latest, &[false, true]), vec![&[2][..]]);
+		assert!(due(&sender.latest, &[false, false]).is_empty());
+	}
+}
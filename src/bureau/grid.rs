@@ -0,0 +1,284 @@
+use hashbrown::HashMap;
+
+use super::math::Vector3;
+
+type Cell = (i32, i32, i32);
+
+/// Smallest cell size, keeps a zero aura radius from making cells infinitely small.
+const MIN_CELL_SIZE: f32 = 1.0;
+
+/// Uniform grid of user positions, used to find users near a position without checking every
+/// user.
+pub struct Grid {
+	cell_size: f32,
+	cells: HashMap<Cell, Vec<i32>>,
+	/// Cell every user in the grid is in.
+	users: HashMap<i32, Cell>,
+}
+
+impl Grid {
+	pub fn new(cell_size: f32) -> Self {
+		Self {
+			cell_size: Self::valid_cell_size(cell_size),
+			cells: HashMap::new(),
+			users: HashMap::new(),
+		}
+	}
+
+	fn valid_cell_size(cell_size: f32) -> f32 {
+		if cell_size.is_finite() {
+			cell_size.max(MIN_CELL_SIZE)
+		} else {
+			MIN_CELL_SIZE
+		}
+	}
+
+	pub fn cell_size(&self) -> f32 {
+		self.cell_size
+	}
+
+	fn cell(&self, pos: &Vector3) -> Cell {
+		// Casts saturate, NaN ends up in cell 0.
+		(
+			(pos.x / self.cell_size).floor() as i32,
+			(pos.y / self.cell_size).floor() as i32,
+			(pos.z / self.cell_size).floor() as i32,
+		)
+	}
+
+	/// Add a user to the grid or move them to the cell `pos` is in.
+	pub fn insert(&mut self, id: i32, pos: &Vector3) {
+		let cell = self.cell(pos);
+
+		if let Some(old) = self.users.insert(id, cell) {
+			if old == cell {
+				return;
+			}
+
+			self.remove_from_cell(id, old);
+		}
+
+		self.cells.entry(cell).or_default().push(id);
+	}
+
+	pub fn contains(&self, id: i32) -> bool {
+		self.users.contains_key(&id)
+	}
+
+	pub fn remove(&mut self, id: i32) {
+		if let Some(cell) = self.users.remove(&id) {
+			self.remove_from_cell(id, cell);
+		}
+	}
+
+	fn remove_from_cell(&mut self, id: i32, cell: Cell) {
+		let Some(ids) = self.cells.get_mut(&cell) else {
+			return;
+		};
+
+		ids.retain(|other| *other != id);
+		if ids.is_empty() {
+			self.cells.remove(&cell);
+		}
+	}
+
+	/// Get every user in a cell that overlaps the cube around `pos` with sides of `2 * radius`.
+	///
+	/// Users further than `radius` can be returned, distances still have to be checked.
+	pub fn query(&self, pos: &Vector3, radius: f32) -> Vec<i32> {
+		let reach = (radius / self.cell_size).ceil();
+
+		// Checking every occupied cell is faster than visiting more cells than there are.
+		if !(reach >= 0.0 && (2.0 * reach + 1.0).powi(3) <= self.cells.len() as f32) {
+			return self.cells.values().flatten().copied().collect();
+		}
+
+		let reach = reach as i32;
+		let (x, y, z) = self.cell(pos);
+		let mut ids = Vec::new();
+
+		for cx in x.saturating_sub(reach)..=x.saturating_add(reach) {
+			for cy in y.saturating_sub(reach)..=y.saturating_add(reach) {
+				for cz in z.saturating_sub(reach)..=z.saturating_add(reach) {
+					if let Some(cell) = self.cells.get(&(cx, cy, cz)) {
+						ids.extend(cell);
+					}
+				}
+			}
+		}
+
+		ids
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::time::Instant;
+
+	use super::*;
+
+	fn sorted(mut ids: Vec<i32>) -> Vec<i32> {
+		ids.sort_unstable();
+		ids
+	}
+
+	/// Grid with enough far away users that queries don't fall back to returning everyone.
+	fn crowded_grid(cell_size: f32) -> Grid {
+		let mut grid = Grid::new(cell_size);
+		for i in 0..100 {
+			grid.insert(1000 + i, &Vector3::new(1000.0 + i as f32 * 10.0, 0.0, 0.0));
+		}
+
+		grid
+	}
+
+	#[test]
+	fn query_finds_users_across_cell_boundaries() {
+		let mut grid = crowded_grid(10.0);
+		grid.insert(1, &Vector3::new(9.9, 0.0, 0.0));
+		grid.insert(2, &Vector3::new(10.1, 0.0, 0.0));
+		grid.insert(3, &Vector3::new(25.0, 0.0, 0.0));
+
+		assert_eq!(grid.cell(&Vector3::new(10.0, 0.0, 0.0)), (1, 0, 0));
+		assert_eq!(
+			sorted(grid.query(&Vector3::new(9.9, 0.0, 0.0), 1.0)),
+			vec![1, 2]
+		);
+		assert_eq!(
+			sorted(grid.query(&Vector3::new(10.1, 0.0, 0.0), 1.0)),
+			vec![1, 2, 3]
+		);
+	}
+
+	#[test]
+	fn negative_coordinates_round_down() {
+		let mut grid = crowded_grid(10.0);
+		grid.insert(1, &Vector3::new(-0.5, -0.5, -0.5));
+		grid.insert(2, &Vector3::new(0.5, 0.5, 0.5));
+		grid.insert(3, &Vector3::new(-25.0, 0.0, 0.0));
+
+		assert_eq!(grid.cell(&Vector3::new(-0.5, -0.5, -0.5)), (-1, -1, -1));
+		assert_eq!(grid.cell(&Vector3::new(-10.0, 0.0, 0.0)), (-1, 0, 0));
+		assert_eq!(
+			sorted(grid.query(&Vector3::new(0.5, 0.5, 0.5), 1.0)),
+			vec![1, 2]
+		);
+		assert_eq!(grid.query(&Vector3::new(-25.0, 0.0, 0.0), 1.0), vec![3]);
+	}
+
+	#[test]
+	fn insert_moves_users_between_cells() {
+		let mut grid = crowded_grid(10.0);
+		grid.insert(1, &Vector3::new(0.0, 0.0, 0.0));
+		grid.insert(1, &Vector3::new(500.0, 0.0, 0.0));
+
+		assert!(grid.contains(1));
+		assert!(grid.query(&Vector3::new(0.0, 0.0, 0.0), 1.0).is_empty());
+		assert_eq!(grid.query(&Vector3::new(500.0, 0.0, 0.0), 1.0), vec![1]);
+	}
+
+	#[test]
+	fn remove_drops_users_and_empty_cells() {
+		let mut grid = crowded_grid(10.0);
+		let cells = grid.cells.len();
+
+		grid.insert(1, &Vector3::new(500.0, 0.0, 0.0));
+		grid.insert(2, &Vector3::new(501.0, 0.0, 0.0));
+		assert_eq!(grid.cells.len(), cells + 1);
+
+		grid.remove(1);
+		assert!(!grid.contains(1));
+		assert_eq!(grid.query(&Vector3::new(500.0, 0.0, 0.0), 1.0), vec![2]);
+
+		grid.remove(2);
+		grid.remove(2);
+		assert_eq!(grid.cells.len(), cells);
+	}
+
+	#[test]
+	fn query_returns_everyone_when_the_cube_has_more_cells_than_are_occupied() {
+		let mut grid = Grid::new(1.0);
+		grid.insert(1, &Vector3::new(0.0, 0.0, 0.0));
+		grid.insert(2, &Vector3::new(1000.0, 0.0, 0.0));
+		grid.insert(3, &Vector3::new(-1000.0, 50.0, 0.0));
+
+		assert_eq!(
+			sorted(grid.query(&Vector3::new(0.0, 0.0, 0.0), 1.0)),
+			vec![1, 2, 3]
+		);
+		assert_eq!(
+			sorted(grid.query(&Vector3::new(0.0, 0.0, 0.0), f32::NAN)),
+			vec![1, 2, 3]
+		);
+		assert_eq!(
+			sorted(grid.query(&Vector3::new(0.0, 0.0, 0.0), f32::INFINITY)),
+			vec![1, 2, 3]
+		);
+	}
+
+	#[test]
+	fn cell_size_is_clamped() {
+		assert_eq!(Grid::new(0.0).cell_size(), MIN_CELL_SIZE);
+		assert_eq!(Grid::new(f32::NAN).cell_size(), MIN_CELL_SIZE);
+		assert_eq!(Grid::new(f32::INFINITY).cell_size(), MIN_CELL_SIZE);
+		assert_eq!(Grid::new(50.0).cell_size(), 50.0);
+	}
+
+	/// Compares the grid with checking every user, run with
+	/// `cargo test --release -- --ignored --nocapture grid_vs_full_scan`.
+	#[test]
+	#[ignore]
+	fn grid_vs_full_scan() {
+		const USERS: i32 = 5000;
+		const RADIUS: f32 = 50.0;
+		const WORLD: f32 = 2000.0;
+
+		// Deterministic positions without pulling in a random number crate.
+		let mut seed = 0x2545_f491_u32;
+		let mut next = || {
+			seed ^= seed << 13;
+			seed ^= seed >> 17;
+			seed ^= seed << 5;
+			seed as f32 / u32::MAX as f32 * WORLD - WORLD / 2.0
+		};
+
+		let positions = (0..USERS)
+			.map(|_| Vector3::new(next(), next(), next()))
+			.collect::<Vec<_>>();
+
+		let mut grid = Grid::new(RADIUS);
+		for (id, pos) in positions.iter().enumerate() {
+			grid.insert(id as i32, pos);
+		}
+
+		let start = Instant::now();
+		let full_scan = positions
+			.iter()
+			.map(|pos| {
+				positions
+					.iter()
+					.filter(|other| pos.distance_sqr(other) <= RADIUS.powi(2))
+					.count()
+			})
+			.collect::<Vec<_>>();
+		let full_scan_time = start.elapsed();
+
+		let start = Instant::now();
+		let grid_scan = positions
+			.iter()
+			.map(|pos| {
+				grid.query(pos, RADIUS)
+					.into_iter()
+					.filter(|id| pos.distance_sqr(&positions[*id as usize]) <= RADIUS.powi(2))
+					.count()
+			})
+			.collect::<Vec<_>>();
+		let grid_time = start.elapsed();
+
+		assert_eq!(full_scan, grid_scan);
+		println!(
+			"{} users, radius {}: full scan {:?}, grid {:?}",
+			USERS, RADIUS, full_scan_time, grid_time
+		);
+	}
+}
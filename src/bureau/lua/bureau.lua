@@ -0,0 +1,19 @@
+local ftbl, users = ...
+
+local master = ftbl.master
+
+local bureau = {
+	port = ftbl.port,
+	started_at = ftbl.started_at,
+	config = ftbl.config,
+	uptime = ftbl.uptime,
+	user_count = ftbl.user_count,
+	is_wls = ftbl.is_wls,
+}
+
+function bureau.master()
+	local id = master()
+	return id and users[id]
+end
+
+package.loaded["bureau"] = bureau
@@ -1,86 +1,179 @@
-local users, user_meta = ...
+local users, user_meta, ftbl, reset_limits, clear_limits, raw_pcall, notify, set_raw_packets = ...
+
+-- The sandbox's pcall rethrows instruction limit errors, errors from one hook shouldn't stop the rest.
+local pcall = raw_pcall or pcall
 
 local hook = {}
 
-local function ins_tbl_ret(tbl, obj)
-	local pos = #tbl + 1
-	tbl[pos] = obj
-	return obj
-end
+-- A plugin is disabled after this many errors in a row.
+local MAX_ERRORS = 10
 
-local think_hooks = {}
-function hook.think(fn)
-	return ins_tbl_ret(think_hooks, fn)
-end
+local hooks = {}
+local handles = {}
+local next_handle = 1
 
-local user_connect_hooks = {}
-function hook.user_connect(fn)
-	return ins_tbl_ret(user_connect_hooks, fn)
-end
+local loaded_plugins = {}
+local disabled_plugins = {}
+local error_counts = {}
 
-local new_user_hooks = {}
-function hook.new_user(fn)
-	return ins_tbl_ret(new_user_hooks, fn)
-end
+-- Name of the plugin whose code is currently running, nil for code that isn't from a plugin.
+local current_plugin = nil
 
-local pos_update_hooks = {}
-function hook.position_update(fn)
-	return ins_tbl_ret(pos_update_hooks, fn)
-end
+local events = {
+	"think",
+	"user_connect",
+	"new_user",
+	"position_update",
+	"transform_update",
+	"movement_violation",
+	"chat_send",
+	"validate_name",
+	"name_change",
+	"avatar_change",
+	"character_update",
+	"private_chat",
+	"raw_packet",
+	"aura_enter",
+	"aura_leave",
+	"user_disconnect",
+	"plugins_loaded",
+}
+
+for _, event in ipairs(events) do
+	hooks[event] = {}
+
+	hook[event] = function(fn)
+		local handle = next_handle
+		next_handle = next_handle + 1
+
+		local list = hooks[event]
+		list[#list + 1] = {
+			fn = fn,
+			plugin = current_plugin,
+			handle = handle,
+		}
+		handles[handle] = event
+
+		if event == "raw_packet" then
+			set_raw_packets(true)
+		end
 
-local trans_update_hooks = {}
-function hook.transform_update(fn)
-	return ins_tbl_ret(trans_update_hooks, fn)
+		return handle
+	end
 end
 
-local chat_send_hooks = {}
-function hook.chat_send(fn)
-	return ins_tbl_ret(chat_send_hooks, fn)
+function hook.remove(handle)
+	local event = handles[handle]
+	if not event then return false end
+
+	handles[handle] = nil
+
+	-- Build a new list so hooks that are currently running aren't skipped.
+	local list = {}
+	for _, entry in ipairs(hooks[event]) do
+		if entry.handle ~= handle then
+			list[#list + 1] = entry
+		end
+	end
+	hooks[event] = list
+
+	if event == "raw_packet" then
+		set_raw_packets(#list > 0)
+	end
+
+	return true
 end
 
-local name_change_hooks = {}
-function hook.name_change(fn)
-	return ins_tbl_ret(name_change_hooks, fn)
+package.loaded["hook"] = hook
+
+local plugins = {}
+
+function plugins.all()
+	local ret = {}
+	for i, name in ipairs(loaded_plugins) do
+		ret[i] = name
+	end
+	return ret
 end
 
-local avatar_change_hooks = {}
-function hook.avatar_change(fn)
-	return ins_tbl_ret(avatar_change_hooks, fn)
+function plugins.current()
+	return current_plugin
 end
 
-local private_chat_hooks = {}
-function hook.private_chat(fn)
-	return ins_tbl_ret(private_chat_hooks, fn)
+function plugins.enabled(name)
+	return not disabled_plugins[name]
 end
 
-local aura_enter_hooks = {}
-function hook.aura_enter(fn)
-	return ins_tbl_ret(aura_enter_hooks, fn)
+function plugins.disable(name)
+	disabled_plugins[name] = true
 end
 
-local aura_leave_hooks = {}
-function hook.aura_leave(fn)
-	return ins_tbl_ret(aura_leave_hooks, fn)
+function plugins.enable(name)
+	disabled_plugins[name] = nil
+	error_counts[name] = nil
 end
 
-local user_disconnect_hooks = {}
-function hook.user_disconnect(fn)
-	return ins_tbl_ret(user_disconnect_hooks, fn)
+package.loaded["plugins"] = plugins
+
+local function plugin_error(what, plugin, err)
+	if not plugin then
+		print(string.format("Error in %s: %s", what, err))
+		return
+	end
+
+	print(string.format("Error in %s from plugin '%s': %s", what, plugin, err))
+
+	local count = (error_counts[plugin] or 0) + 1
+	error_counts[plugin] = count
+
+	if count >= MAX_ERRORS then
+		print(string.format("Plugin '%s' errored %d times in a row and has been disabled.", plugin, count))
+		disabled_plugins[plugin] = true
+	end
 end
 
-local plugins_loaded_hooks = {}
-function hook.plugins_loaded(fn)
-	return ins_tbl_ret(plugins_loaded_hooks, fn)
+-- Call fn on behalf of a plugin, errors are printed and count towards disabling the plugin.
+-- Returns false if the plugin is disabled or fn errored.
+local function plugin_call(what, plugin, fn, ...)
+	if plugin and disabled_plugins[plugin] then
+		return false
+	end
+
+	if reset_limits then
+		reset_limits()
+	end
+
+	local prev = current_plugin
+	current_plugin = plugin
+	local ok, ret = pcall(fn, ...)
+	current_plugin = prev
+
+	if not ok then
+		-- The hook could have run out of memory, reporting the error needs some.
+		if clear_limits then
+			clear_limits()
+		end
+
+		plugin_error(what, plugin, ret)
+		return false
+	end
+
+	if plugin then
+		error_counts[plugin] = nil
+	end
+
+	return true, ret
 end
 
-package.loaded["hook"] = hook
+local function run_hooks(event, ...)
+	notify(event, ...)
 
-local function run_hooks(tbl, ...)
-	for i = 1, #tbl do
-		local fn = tbl[i]
-		local ret = fn(...)
+	local list = hooks[event]
+	for i = 1, #list do
+		local entry = list[i]
+		local ok, ret = plugin_call("'" .. event .. "' hook", entry.plugin, entry.fn, ...)
 
-		if ret then
+		if ok and ret then
 			return ret
 		end
 	end
@@ -89,31 +182,61 @@ end
 local Vector = require("vector")
 local Basis = require("basis")
 
-return {
+local function create_user(id, name, avatar, ip)
+	local u = setmetatable({
+		id = id,
+		name = name,
+		avatar = avatar,
+		ip = ip,
+		_pos = Vector(0, 0, 0),
+		_rot = Basis(),
+	}, user_meta)
+	users[id] = u
+
+	return u
+end
+
+-- Run the hooks for a name or avatar change, hooks can rewrite the value by returning a string
+-- or reject it by returning an empty string.
+-- Returns the value to apply (nil if rejected) and what the hooks returned.
+local function change(event, field, u, value)
+	local ret = run_hooks(event, u, value, u[field])
+	if type(ret) ~= "string" then
+		ret = nil
+	end
+
+	value = ret or value
+	if value == "" then
+		return nil, ret
+	end
+
+	u[field] = value
+
+	return value, ret
+end
+
+local entry = {
 	think = function()
-		return run_hooks(think_hooks)
+		return run_hooks("think")
 	end,
 	user_connect = function(addr)
-		return run_hooks(user_connect_hooks, addr)
+		return run_hooks("user_connect", addr)
 	end,
 	new_user = function(id, name, avatar, ip)
-		local u = setmetatable({
-			id = id,
-			name = name,
-			avatar = avatar,
-			ip = ip,
-			_pos = Vector(0, 0, 0),
-			_rot = Basis(),
-		}, user_meta)
-		users[id] = u
-
-		return run_hooks(new_user_hooks, u, name, avatar)
+		local u = create_user(id, name, avatar, ip)
+
+		return run_hooks("new_user", u, name, avatar)
+	end,
+	add_user = function(id, name, avatar, ip, pos, rot)
+		local u = create_user(id, name, avatar, ip)
+		u._pos = Vector(pos[1], pos[2], pos[3])
+		u._rot:set(rot)
 	end,
 	pos_update = function(id, x, y, z)
 		local user = users[id]
 		user._pos = Vector(x, y, z)
 
-		return run_hooks(pos_update_hooks, users[id], Vector(x, y, z))
+		return run_hooks("position_update", users[id], Vector(x, y, z))
 	end,
 	trans_update = function(id, arr)
 		local user = users[id]
@@ -121,51 +244,125 @@ return {
 		rot:set(arr)
 		user._rot = rot
 
-		return run_hooks(trans_update_hooks, users[id])
+		return run_hooks("transform_update", users[id])
+	end,
+	movement_violation = function(id, x, y, z, reason)
+		local user = users[id]
+		if not user then return end
+
+		return run_hooks("movement_violation", user, Vector(x, y, z), reason) == true
 	end,
 	chat_send = function(id, msg)
-		return run_hooks(chat_send_hooks, users[id], msg)
+		return run_hooks("chat_send", users[id], msg)
+	end,
+	validate_name = function(id, ip, name, suggested, reason)
+		local ret = run_hooks("validate_name", id, ip, name, suggested, reason)
+		if type(ret) == "string" then
+			return ret
+		end
 	end,
 	name_change = function(id, name)
 		local u = users[id]
 		if not u then return end
 
-		local old = u.name
-		u.name = name
-
-		return run_hooks(name_change_hooks, u, name, old)
+		local _, ret = change("name_change", "name", u, name)
+		return ret
 	end,
 	avatar_change = function(id, avatar)
 		local u = users[id]
 		if not u then return end
 
-		local old = u.avatar
-		u.avatar = avatar
+		local _, ret = change("avatar_change", "avatar", u, avatar)
+		return ret
+	end,
+	character_update = function(id, data)
+		local u = users[id]
+		if not u then return end
 
-		return run_hooks(avatar_change_hooks, u, avatar, old)
+		return run_hooks("character_update", u, data)
 	end,
 	private_chat = function(id1, id2, msg)
-		return run_hooks(private_chat_hooks, users[id1], users[id2], msg)
+		return run_hooks("private_chat", users[id1], users[id2], msg)
+	end,
+	raw_packet = function(id, section_type, bytes)
+		local ret = run_hooks("raw_packet", users[id], section_type, bytes)
+		if type(ret) == "string" then
+			return ret
+		end
 	end,
 	aura_enter = function(id1, id2)
 		local u1 = users[id1]
 		local u2 = users[id2]
 
-		return run_hooks(aura_enter_hooks, u1, u2)
+		return run_hooks("aura_enter", u1, u2)
 	end,
 	aura_leave = function(id1, id2)
 		local u1 = users[id1]
 		local u2 = users[id2]
 
-		return run_hooks(aura_leave_hooks, u1, u2)
+		return run_hooks("aura_leave", u1, u2)
 	end,
 	user_disconnect = function(id)
 		local u = users[id]
 		users[id] = nil
 
-		return run_hooks(user_disconnect_hooks, u)
+		return run_hooks("user_disconnect", u)
 	end,
 	plugins_loaded = function()
-		return run_hooks(plugins_loaded_hooks)
+		return run_hooks("plugins_loaded")
+	end,
+	plugin_call = plugin_call,
+	load_plugin = function(name, fn)
+		loaded_plugins[#loaded_plugins + 1] = name
+
+		local prev = current_plugin
+		current_plugin = name
+		local ok, err = pcall(fn)
+		current_plugin = prev
+
+		if not ok then
+			error(err, 0)
+		end
 	end
 }
+
+local spawn_bot = ftbl.spawn_bot
+local user_manager = package.loaded["users"]
+
+function user_manager.spawn_bot(name, avatar, data)
+	avatar = avatar or require("avatars").default()
+
+	local id = spawn_bot(name, avatar, data or "")
+	if not id then return nil end
+
+	entry.new_user(id, name, avatar, nil)
+
+	return users[id]
+end
+
+local set_name = ftbl.set_name
+local set_avatar = ftbl.set_avatar
+local set_character_data = ftbl.set_character_data
+
+function user_meta:set_name(name)
+	name = change("name_change", "name", self, name)
+	if not name then return false end
+
+	set_name(self.id, name)
+	return true
+end
+
+function user_meta:set_avatar(avatar)
+	avatar = change("avatar_change", "avatar", self, avatar)
+	if not avatar then return false end
+
+	set_avatar(self.id, avatar)
+	return true
+end
+
+function user_meta:set_character_data(data)
+	set_character_data(self.id, data)
+	entry.character_update(self.id, data)
+end
+
+return entry
@@ -0,0 +1,178 @@
+-- Strips the standard library down to what untrusted plugins are allowed to use.
+-- Returns a function that gives a plugin environment access to files in its own directory and
+-- the real pcall, which hook.lua needs to keep calling hooks after one runs out of instructions.
+
+local limit_exceeded = ...
+
+-- Instruction count hooks don't run inside of compiled traces.
+jit.off()
+jit.flush()
+jit = nil
+package.loaded["jit"] = nil
+
+local raw_io = io
+local raw_loadstring = loadstring
+local raw_pcall = pcall
+local raw_xpcall = xpcall
+local raw_resume = coroutine.resume
+
+-- Errors from the instruction limit can't be caught, otherwise a plugin could catch them in a
+-- loop forever.
+local function rethrow_limit(ok, ...)
+	if not ok and limit_exceeded() then
+		error((...), 0)
+	end
+
+	return ok, ...
+end
+
+function pcall(fn, ...)
+	return rethrow_limit(raw_pcall(fn, ...))
+end
+
+function xpcall(fn, handler, ...)
+	-- Instruction hooks don't run inside of a handler called from one.
+	local function limited_handler(...)
+		if limit_exceeded() then
+			return ...
+		end
+
+		return handler(...)
+	end
+
+	return rethrow_limit(raw_xpcall(fn, limited_handler, ...))
+end
+
+function coroutine.resume(co, ...)
+	return rethrow_limit(raw_resume(co, ...))
+end
+
+-- LuaJIT doesn't verify bytecode, loading a crafted chunk can crash the bureau.
+function loadstring(s, chunkname)
+	if type(s) == "string" and s:byte(1) == 27 then
+		return nil, "binary chunks are not allowed"
+	end
+
+	return raw_loadstring(s, chunkname)
+end
+
+function load(fn, chunkname)
+	if type(fn) ~= "function" then
+		return loadstring(fn, chunkname)
+	end
+
+	local parts = {}
+	while true do
+		local part = fn()
+		if part == nil or part == "" then break end
+		parts[#parts + 1] = part
+	end
+
+	return loadstring(table.concat(parts), chunkname)
+end
+
+dofile = nil
+loadfile = nil
+
+local safe_os = {
+	clock = os.clock,
+	date = os.date,
+	difftime = os.difftime,
+	time = os.time,
+}
+os = safe_os
+package.loaded["os"] = safe_os
+
+local safe_io = {
+	write = raw_io.write,
+	type = raw_io.type,
+	stdout = raw_io.stdout,
+	stderr = raw_io.stderr,
+}
+io = safe_io
+package.loaded["io"] = safe_io
+
+local function resolve(dir, path)
+	if type(path) ~= "string" then
+		error("expected a path, got " .. type(path), 3)
+	end
+
+	if path:find("%z") or path:find("^[/\\]") or path:find(":") then
+		return nil, path .. ": path not allowed"
+	end
+
+	for part in path:gmatch("[^/\\]+") do
+		if part == ".." then
+			return nil, path .. ": path not allowed"
+		end
+	end
+
+	return dir .. "/" .. path
+end
+
+package.loadlib = nil
+package.cpath = ""
+
+-- package.path can be changed by plugins, only search the plugins directory.
+package.loaders[2] = function(name)
+	local path, err = resolve("plugins", name:gsub("%.", "/") .. ".lua")
+	if not path then return "\n\t" .. err end
+
+	local f = raw_io.open(path, "r")
+	if not f then return "\n\tno file '" .. path .. "'" end
+
+	local src = f:read("*a")
+	f:close()
+
+	local fn, load_err = loadstring(src, "=" .. path)
+	if not fn then error(load_err, 0) end
+
+	return fn
+end
+package.loaders[3] = nil
+package.loaders[4] = nil
+
+local function plugin_env(env, dir)
+	local plugin_io = setmetatable({}, { __index = safe_io })
+
+	function plugin_io.open(path, mode)
+		local resolved, err = resolve(dir, path)
+		if not resolved then return nil, err end
+
+		return raw_io.open(resolved, mode)
+	end
+
+	function plugin_io.lines(path)
+		local resolved, err = resolve(dir, path)
+		if not resolved then error(err, 2) end
+
+		return raw_io.lines(resolved)
+	end
+
+	local function plugin_loadfile(path)
+		local resolved, err = resolve(dir, path)
+		if not resolved then return nil, err end
+
+		local f, open_err = raw_io.open(resolved, "r")
+		if not f then return nil, open_err end
+
+		local src = f:read("*a")
+		f:close()
+
+		local fn, load_err = loadstring(src, "=" .. resolved)
+		if not fn then return nil, load_err end
+
+		return setfenv(fn, env)
+	end
+
+	env.io = plugin_io
+	env.loadfile = plugin_loadfile
+	env.dofile = function(path)
+		local fn, err = plugin_loadfile(path)
+		if not fn then error(err, 2) end
+
+		return fn()
+	end
+end
+
+return plugin_env, raw_pcall
@@ -0,0 +1,37 @@
+local ftbl = ...
+
+local plugins = require("plugins")
+
+local get = ftbl.get
+local set = ftbl.set
+local keys = ftbl.keys
+
+local storage = {}
+
+-- Every plugin gets its own namespace.
+local function namespace()
+	local plugin = plugins.current()
+	if not plugin then
+		error("storage can only be used by plugins", 3)
+	end
+
+	return plugin
+end
+
+function storage.get(key)
+	return get(namespace(), key)
+end
+
+function storage.set(key, value)
+	set(namespace(), key, value)
+end
+
+function storage.remove(key)
+	set(namespace(), key, nil)
+end
+
+function storage.keys()
+	return keys(namespace())
+end
+
+package.loaded["storage"] = storage
@@ -0,0 +1,42 @@
+local sched, plugin_call = ...
+
+local plugins = require("plugins")
+
+local task = {}
+
+function task.spawn(fn, ...)
+	assert(type(fn) == "function", "task must be a function")
+	return sched.add(coroutine.create(fn), plugins.current(), ...)
+end
+
+function task.sleep(sec)
+	assert(type(sec) == "number", "sleep time must be a number")
+	coroutine.yield("sleep", sec)
+end
+
+function task.wait_for(event, timeout)
+	assert(type(event) == "string", "event must be a string")
+	return coroutine.yield("event", event, timeout)
+end
+
+task.notify = sched.notify
+
+package.loaded["task"] = task
+
+local function resume(co, ...)
+	local ret = { coroutine.resume(co, ...) }
+	if not ret[1] then
+		error(ret[2], 0)
+	end
+
+	return ret
+end
+
+return function(id, plugin, co, ...)
+	local ok, ret = plugin_call("task " .. id, plugin, resume, co, ...)
+	if not ok then
+		return false
+	end
+
+	return true, ret[2], ret[3], ret[4]
+end
@@ -0,0 +1,73 @@
+local now, plugin_call = ...
+
+local plugins = require("plugins")
+
+local timer = {}
+
+local timers = {}
+local next_id = 1
+
+local function add(delay, interval, fn)
+	assert(type(delay) == "number", "delay must be a number")
+	assert(type(fn) == "function", "callback must be a function")
+
+	local id = next_id
+	next_id = next_id + 1
+
+	timers[id] = {
+		time = now() + delay,
+		interval = interval,
+		fn = fn,
+		plugin = plugins.current(),
+	}
+
+	return id
+end
+
+function timer.after(sec, fn)
+	return add(sec, nil, fn)
+end
+
+function timer.every(sec, fn)
+	assert(type(sec) == "number" and sec > 0, "interval must be a positive number")
+	return add(sec, sec, fn)
+end
+
+function timer.cancel(id)
+	if not timers[id] then return false end
+
+	timers[id] = nil
+	return true
+end
+
+timer.now = now
+
+package.loaded["timer"] = timer
+
+return function()
+	local time = now()
+
+	local due = {}
+	for id, t in pairs(timers) do
+		if t.time <= time then
+			due[#due + 1] = id
+		end
+	end
+	table.sort(due)
+
+	for _, id in ipairs(due) do
+		local t = timers[id]
+
+		-- Could've been cancelled by an earlier timer.
+		if t then
+			if t.interval then
+				-- Skip missed runs instead of running them all at once.
+				t.time = math.max(t.time + t.interval, time)
+			else
+				timers[id] = nil
+			end
+
+			plugin_call("timer " .. id, t.plugin, t.fn)
+		end
+	end
+end
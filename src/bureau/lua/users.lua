@@ -6,8 +6,25 @@ local ftbl = ...
 local set_pos = ftbl.set_pos
 local set_rot = ftbl.set_rot
 local send_msg = ftbl.send_msg
+local send_aura_msg = ftbl.send_aura_msg
+local broadcast = ftbl.broadcast
 local send_packet = ftbl.send_packet
 local disconnect = ftbl.disconnect
+local get_aura = ftbl.aura
+local character_data = ftbl.character_data
+local connected_at = ftbl.connected_at
+local is_master = ftbl.is_master
+local addr = ftbl.addr
+local initialized = ftbl.initialized
+local is_bot = ftbl.is_bot
+local aura_radius = ftbl.aura_radius
+local set_aura_radius = ftbl.set_aura_radius
+local hidden = ftbl.hidden
+local set_hidden = ftbl.set_hidden
+local aura_override = ftbl.aura_override
+local set_aura_override = ftbl.set_aura_override
+
+local users = {}
 
 local user_meta = {}
 user_meta.__index = user_meta
@@ -34,20 +51,85 @@ function user_meta:rot()
 	return self._rot:clone()
 end
 
-function user_meta:send_msg(msg)
-	send_msg(self.id, msg)
+function user_meta:send_msg(msg, from)
+	send_msg(self.id, msg, from and from.id)
+end
+
+function user_meta:send_aura_msg(msg, from)
+	send_aura_msg(self.id, msg, from and from.id)
 end
 
 function user_meta:send_packet(msg)
+	if type(msg) ~= "string" then
+		msg = msg:bytes()
+	end
+
 	send_packet(self.id, msg)
 end
 
+function user_meta:aura()
+	local ret = {}
+	for _, id in ipairs(get_aura(self.id)) do
+		local other = users[id]
+		if other then
+			ret[#ret + 1] = other
+		end
+	end
+	return ret
+end
+
+function user_meta:aura_radius()
+	return aura_radius(self.id)
+end
+
+function user_meta:set_aura_radius(radius)
+	set_aura_radius(self.id, radius)
+end
+
+function user_meta:hidden()
+	return hidden(self.id)
+end
+
+function user_meta:set_hidden(value)
+	set_hidden(self.id, value)
+end
+
+function user_meta:aura_override(other)
+	return aura_override(self.id, other.id)
+end
+
+function user_meta:set_aura_override(other, include)
+	set_aura_override(self.id, other.id, include)
+end
+
+function user_meta:character_data()
+	return character_data(self.id)
+end
+
+function user_meta:connected_at()
+	return connected_at(self.id)
+end
+
+function user_meta:is_master()
+	return is_master(self.id)
+end
+
+function user_meta:addr()
+	return addr(self.id)
+end
+
+function user_meta:initialized()
+	return initialized(self.id)
+end
+
+function user_meta:is_bot()
+	return is_bot(self.id)
+end
+
 function user_meta:__tostring()
 	return string.format("User: '%s' (%s)", self.name, self.id)
 end
 
-local users = {}
-
 local user_manager = {}
 
 function user_manager.all()
@@ -62,6 +144,10 @@ function user_manager.get(id)
 	return users[id]
 end
 
+function user_manager.broadcast(msg, from)
+	broadcast(msg, from and from.id)
+end
+
 package.loaded["users"] = user_manager
 
 return users, user_meta
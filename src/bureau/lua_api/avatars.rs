@@ -0,0 +1,45 @@
+use mlua::{Lua, Table};
+
+use crate::bureau::{avatars::AvatarCatalog, user_list::AwesomeCell};
+
+/// Create the `avatars` module.
+pub fn create_table(lua: &Lua, avatars: &AwesomeCell<AvatarCatalog>) -> mlua::Result<Table> {
+	let tbl = lua.create_table()?;
+
+	tbl.set(
+		"list",
+		lua.create_function({
+			let avatars = avatars.clone();
+			move |_, ()| {
+				let mut list = avatars
+					.get()
+					.avatars()
+					.map(|avatars| avatars.iter().cloned().collect::<Vec<_>>());
+
+				if let Some(list) = &mut list {
+					list.sort();
+				}
+
+				Ok(list)
+			}
+		})?,
+	)?;
+
+	tbl.set(
+		"allowed",
+		lua.create_function({
+			let avatars = avatars.clone();
+			move |_, avatar: String| Ok(avatars.get().is_allowed(&avatar))
+		})?,
+	)?;
+
+	tbl.set(
+		"default",
+		lua.create_function({
+			let avatars = avatars.clone();
+			move |_, ()| Ok(avatars.get().default().clone())
+		})?,
+	)?;
+
+	Ok(tbl)
+}
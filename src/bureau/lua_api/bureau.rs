@@ -0,0 +1,224 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use mlua::{IntoLua, Lua, MetaMethod, Table, UserData, UserDataMethods, Value};
+
+use crate::bureau::{
+	BureauConfig,
+	user_list::{AwesomeCell, UserList},
+};
+
+/// Runtime info about a bureau that isn't part of its config.
+#[derive(Clone, Copy)]
+pub struct BureauInfo {
+	pub port: u16,
+	pub started: SystemTime,
+}
+
+/// Live view of the bureau's config, reads always see the current values and writes to the
+/// fields that can change while running apply immediately.
+struct Config {
+	config: AwesomeCell<BureauConfig>,
+	user_list: AwesomeCell<UserList>,
+}
+
+/// Convert a value set on the config to a finite number that isn't negative.
+fn non_negative(lua: &Lua, key: &str, value: Value) -> mlua::Result<f32> {
+	let n = lua.unpack::<f32>(value)?;
+	if !n.is_finite() || n < 0.0 {
+		return Err(mlua::Error::external(format!("{} can't be negative", key)));
+	}
+
+	Ok(n)
+}
+
+impl UserData for Config {
+	fn add_methods<M: UserDataMethods<Self>>(methods: &mut M) {
+		methods.add_meta_method(MetaMethod::Index, |lua, this, key: String| {
+			let config = this.config.get();
+
+			match key.as_str() {
+				"connect_timeout" => config.connect_timeout.into_lua(lua),
+				"max_users" => config.max_users.into_lua(lua),
+				"max_queue" => config.max_queue.into_lua(lua),
+				"aura_radius" => config.aura_radius.into_lua(lua),
+				"aura_leave_radius" => config.aura_leave_radius.into_lua(lua),
+				"aura_dwell" => config.aura_dwell.into_lua(lua),
+				"max_aura_changes" => config.max_aura_changes.into_lua(lua),
+				"max_update_rate" => config.max_update_rate.into_lua(lua),
+				"update_falloff" => config.update_falloff.into_lua(lua),
+				"plugins" => config.plugins.clone().into_lua(lua),
+				"sandbox" => match &config.sandbox {
+					Some(sandbox) => {
+						let tbl = lua.create_table()?;
+						tbl.set("instruction_limit", sandbox.instruction_limit)?;
+						tbl.set("memory_limit", sandbox.memory_limit)?;
+						tbl.into_lua(lua)
+					}
+					None => Ok(Value::Nil),
+				},
+				"motd" => config.motd.clone().into_lua(lua),
+				"avatars" => config
+					.avatars
+					.as_ref()
+					.map(|path| path.to_string_lossy().into_owned())
+					.into_lua(lua),
+				"default_avatar" => config.default_avatar.clone().into_lua(lua),
+				"names" => {
+					let tbl = lua.create_table()?;
+					tbl.set("min_length", config.names.min_length)?;
+					tbl.set("max_length", config.names.max_length)?;
+					tbl.set("charset", config.names.charset.as_str())?;
+					tbl.set("unique", config.names.unique)?;
+					tbl.set("reserved", config.names.reserved.clone())?;
+					tbl.into_lua(lua)
+				}
+				"world_bounds" => match &config.movement.bounds {
+					Some(bounds) => {
+						let tbl = lua.create_table()?;
+						tbl.set("min", [bounds.min.x, bounds.min.y, bounds.min.z])?;
+						tbl.set("max", [bounds.max.x, bounds.max.y, bounds.max.z])?;
+						tbl.into_lua(lua)
+					}
+					None => Ok(Value::Nil),
+				},
+				"max_speed" => config.movement.max_speed.into_lua(lua),
+				"count_bots" => config.count_bots.into_lua(lua),
+				"storage" => config
+					.storage
+					.dir()
+					.to_string_lossy()
+					.into_owned()
+					.into_lua(lua),
+				"wrl" => config.wrl.clone().into_lua(lua),
+				_ => Ok(Value::Nil),
+			}
+		});
+
+		methods.add_meta_method(
+			MetaMethod::NewIndex,
+			|lua, this, (key, value): (String, Value)| {
+				match key.as_str() {
+					"aura_radius" => {
+						let radius = non_negative(lua, &key, value)?;
+
+						// The bureau notices the change and updates every aura.
+						this.config.get_mut().aura_radius = radius;
+					}
+					"aura_leave_radius" => {
+						let radius = match value {
+							Value::Nil => None,
+							value => Some(non_negative(lua, &key, value)?),
+						};
+
+						this.config.get_mut().aura_leave_radius = radius;
+					}
+					"aura_dwell" => this.config.get_mut().aura_dwell = lua.unpack(value)?,
+					"max_aura_changes" => {
+						this.config.get_mut().max_aura_changes = lua.unpack(value)?
+					}
+					"max_update_rate" => {
+						this.config.get_mut().max_update_rate = non_negative(lua, &key, value)?
+					}
+					"update_falloff" => {
+						this.config.get_mut().update_falloff = non_negative(lua, &key, value)?
+					}
+					"max_speed" => {
+						let max_speed = match value {
+							Value::Nil => None,
+							value => Some(non_negative(lua, &key, value)?),
+						};
+
+						this.config.get_mut().movement.max_speed = max_speed;
+					}
+					"max_users" => {
+						let max_users = lua.unpack::<i32>(value)?;
+						if max_users <= 0 {
+							return Err(mlua::Error::external("max_users has to be positive"));
+						}
+
+						this.config.get_mut().max_users = max_users;
+						this.user_list.get_mut().set_max_users(max_users);
+					}
+					"motd" => this.config.get_mut().motd = lua.unpack(value)?,
+					"count_bots" => {
+						let count_bots = lua.unpack::<bool>(value)?;
+
+						this.config.get_mut().count_bots = count_bots;
+						this.user_list.get_mut().set_count_bots(count_bots);
+					}
+					_ => {
+						return Err(mlua::Error::external(format!(
+							"config.{} can't be changed while running",
+							key
+						)));
+					}
+				}
+
+				Ok(())
+			},
+		);
+	}
+}
+
+/// Create the table of functions used by bureau.lua.
+pub fn create_table(
+	lua: &Lua,
+	config: &AwesomeCell<BureauConfig>,
+	user_list: &AwesomeCell<UserList>,
+	info: BureauInfo,
+) -> mlua::Result<Table> {
+	let tbl = lua.create_table()?;
+
+	tbl.set("port", info.port)?;
+	tbl.set(
+		"started_at",
+		info.started
+			.duration_since(UNIX_EPOCH)
+			.unwrap_or_default()
+			.as_secs_f64(),
+	)?;
+
+	tbl.set(
+		"config",
+		Config {
+			config: config.clone(),
+			user_list: user_list.clone(),
+		},
+	)?;
+
+	tbl.set(
+		"uptime",
+		lua.create_function(move |_, ()| {
+			Ok(SystemTime::now()
+				.duration_since(info.started)
+				.unwrap_or_default()
+				.as_secs_f64())
+		})?,
+	)?;
+
+	tbl.set(
+		"user_count",
+		lua.create_function({
+			let user_list = user_list.clone();
+			move |_, ()| Ok(user_list.get().user_count())
+		})?,
+	)?;
+
+	tbl.set(
+		"master",
+		lua.create_function({
+			let user_list = user_list.clone();
+			move |_, ()| Ok(user_list.get().master_id())
+		})?,
+	)?;
+
+	tbl.set(
+		"is_wls",
+		lua.create_function({
+			let config = config.clone();
+			move |_, ()| Ok(config.get().wrl.is_some())
+		})?,
+	)?;
+
+	Ok(tbl)
+}
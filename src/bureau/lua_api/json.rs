@@ -0,0 +1,55 @@
+use mlua::{Lua, LuaSerdeExt, SerializeOptions, Table, Value};
+
+/// Options for turning JSON into Lua, `null` becomes `nil`.
+pub fn to_lua_options() -> SerializeOptions {
+	SerializeOptions::new()
+		.serialize_none_to_null(false)
+		.serialize_unit_to_null(false)
+}
+
+/// Create the `json` module.
+pub fn create_table(lua: &Lua) -> mlua::Result<Table> {
+	let tbl = lua.create_table()?;
+
+	tbl.set(
+		"encode",
+		lua.create_function(|lua, (value, pretty): (Value, Option<bool>)| {
+			let value = lua
+				.from_value::<serde_json::Value>(value)
+				.map_err(|e| mlua::Error::runtime(format!("can't encode value as JSON, {}", e)))?;
+
+			let json = if pretty.unwrap_or(false) {
+				serde_json::to_string_pretty(&value)
+			} else {
+				serde_json::to_string(&value)
+			};
+
+			json.map_err(|e| mlua::Error::runtime(format!("can't encode value as JSON, {}", e)))
+		})?,
+	)?;
+
+	tbl.set(
+		"decode",
+		lua.create_function(|lua, json: mlua::String| {
+			let value = serde_json::from_slice::<serde_json::Value>(&json.as_bytes())
+				.map_err(|e| mlua::Error::runtime(format!("invalid JSON, {}", e)))?;
+
+			lua.to_value_with(&value, to_lua_options())
+		})?,
+	)?;
+
+	tbl.set(
+		"array",
+		lua.create_function(|lua, tbl: Option<Table>| {
+			let tbl = match tbl {
+				Some(tbl) => tbl,
+				None => lua.create_table()?,
+			};
+			tbl.set_metatable(Some(lua.array_metatable()))?;
+
+			Ok(tbl)
+		})?,
+	)?;
+
+	Ok(tbl)
+}
@@ -0,0 +1,576 @@
+mod avatars;
+mod bureau;
+mod json;
+mod packet;
+mod sandbox;
+mod storage;
+mod task;
+mod users;
+
+use std::{
+	cell::Cell,
+	fs,
+	io::{self, ErrorKind},
+	net::{IpAddr, SocketAddr},
+	path::Path,
+	rc::Rc,
+	time::Instant,
+};
+
+use mlua::{ChunkMode, FromLuaMulti, Function, IntoLuaMulti, Lua, RegistryKey, Table};
+use sandbox::Sandbox;
+use task::Tasks;
+
+pub use bureau::BureauInfo;
+pub use sandbox::SandboxConfig;
+
+use super::{
+	BureauConfig,
+	avatars::AvatarCatalog,
+	math::{Mat3, Vector3},
+	user_list::{AwesomeCell, UserList},
+};
+
+struct Funcs {
+	think: RegistryKey,
+	user_connect: RegistryKey,
+	new_user: RegistryKey,
+	pos_update: RegistryKey,
+	trans_update: RegistryKey,
+	chat_send: RegistryKey,
+	movement_violation: RegistryKey,
+	validate_name: RegistryKey,
+	name_change: RegistryKey,
+	avatar_change: RegistryKey,
+	character_update: RegistryKey,
+	private_chat: RegistryKey,
+	raw_packet: RegistryKey,
+	user_disconnect: RegistryKey,
+	plugins_loaded: RegistryKey,
+	add_user: RegistryKey,
+	load_plugin: RegistryKey,
+	timers: RegistryKey,
+	resume_task: RegistryKey,
+	tasks: AwesomeCell<Tasks>,
+	/// Set while any raw_packet hooks are registered.
+	raw_packets: Rc<Cell<bool>>,
+}
+
+#[allow(unused_mut)]
+impl Funcs {
+	pub fn init(
+		lua: &mut Lua,
+		user_list: AwesomeCell<UserList>,
+		sandbox: Option<&Rc<Sandbox>>,
+		config: &AwesomeCell<BureauConfig>,
+		info: BureauInfo,
+	) -> mlua::Result<Self> {
+		let tbl = users::create_table(lua, &user_list)?;
+
+		lua.load(include_str!("../lua/vector.lua")).exec()?;
+		lua.load(include_str!("../lua/basis.lua")).exec()?;
+
+		let (users, user_meta): (Table, Table) =
+			lua.load(include_str!("../lua/users.lua")).call(&tbl)?;
+
+		let reset_limits = sandbox
+			.map(|sandbox| sandbox.reset_function(lua))
+			.transpose()?;
+		let clear_limits = sandbox
+			.map(|sandbox| sandbox.clear_function(lua))
+			.transpose()?;
+		let raw_pcall = sandbox.map(|sandbox| sandbox.raw_pcall(lua)).transpose()?;
+
+		let tasks = AwesomeCell::new(Tasks::default());
+
+		let raw_packets = Rc::new(Cell::new(false));
+		let set_raw_packets = lua.create_function({
+			let raw_packets = raw_packets.clone();
+			move |_, enabled: bool| {
+				raw_packets.set(enabled);
+				Ok(())
+			}
+		})?;
+
+		lua.load(include_str!("../lua/bureau.lua")).call::<()>((
+			bureau::create_table(lua, config, &user_list, info)?,
+			users.clone(),
+		))?;
+
+		let tbl: Table = lua.load(include_str!("../lua/hook.lua")).call((
+			users,
+			user_meta,
+			tbl,
+			reset_limits,
+			clear_limits,
+			raw_pcall,
+			Tasks::notify_function(&tasks, lua)?,
+			set_raw_packets,
+		))?;
+
+		let start = Instant::now();
+		let timers: Function = lua.load(include_str!("../lua/timer.lua")).call((
+			lua.create_function(move |_, ()| Ok(start.elapsed().as_secs_f64()))?,
+			tbl.get::<Function>("plugin_call")?,
+		))?;
+
+		let resume_task: Function = lua.load(include_str!("../lua/task.lua")).call((
+			Tasks::create_table(&tasks, lua)?,
+			tbl.get::<Function>("plugin_call")?,
+		))?;
+
+		let storage = config.get().storage.clone();
+		lua.load(include_str!("../lua/storage.lua"))
+			.call::<()>(storage::create_table(lua, &storage)?)?;
+
+		Ok(Self {
+			think: lua.create_registry_value(tbl.get::<Function>("think")?)?,
+			user_connect: lua.create_registry_value(tbl.get::<Function>("user_connect")?)?,
+			new_user: lua.create_registry_value(tbl.get::<Function>("new_user")?)?,
+			pos_update: lua.create_registry_value(tbl.get::<Function>("pos_update")?)?,
+			trans_update: lua.create_registry_value(tbl.get::<Function>("trans_update")?)?,
+			chat_send: lua.create_registry_value(tbl.get::<Function>("chat_send")?)?,
+			movement_violation: lua
+				.create_registry_value(tbl.get::<Function>("movement_violation")?)?,
+			validate_name: lua.create_registry_value(tbl.get::<Function>("validate_name")?)?,
+			name_change: lua.create_registry_value(tbl.get::<Function>("name_change")?)?,
+			avatar_change: lua.create_registry_value(tbl.get::<Function>("avatar_change")?)?,
+			character_update: lua
+				.create_registry_value(tbl.get::<Function>("character_update")?)?,
+			private_chat: lua.create_registry_value(tbl.get::<Function>("private_chat")?)?,
+			raw_packet: lua.create_registry_value(tbl.get::<Function>("raw_packet")?)?,
+			user_disconnect: lua.create_registry_value(tbl.get::<Function>("user_disconnect")?)?,
+			plugins_loaded: lua.create_registry_value(tbl.get::<Function>("plugins_loaded")?)?,
+			add_user: lua.create_registry_value(tbl.get::<Function>("add_user")?)?,
+			load_plugin: lua.create_registry_value(tbl.get::<Function>("load_plugin")?)?,
+			timers: lua.create_registry_value(timers)?,
+			resume_task: lua.create_registry_value(resume_task)?,
+			tasks,
+			raw_packets,
+		})
+	}
+}
+
+pub struct LuaApi {
+	lua: Lua,
+	funcs: Funcs,
+	sandbox: Option<Rc<Sandbox>>,
+	user_list: AwesomeCell<UserList>,
+	avatars: AwesomeCell<AvatarCatalog>,
+	config: AwesomeCell<BureauConfig>,
+	info: BureauInfo,
+}
+
+/// Run a plugin's init.lua in its own environment, globals it sets won't be visible to other
+/// plugins.
+fn load_plugin(
+	lua: &mut Lua,
+	funcs: &Funcs,
+	sandbox: Option<&Rc<Sandbox>>,
+	name: &str,
+	dir: &Path,
+) -> mlua::Result<()> {
+	let path = dir.join("init.lua");
+	let chunkname = format!("={:?}", path);
+
+	let env = lua.create_table()?;
+	let env_meta = lua.create_table()?;
+	env_meta.set("__index", lua.globals())?;
+	env.set_metatable(Some(env_meta))?;
+
+	if let Some(sandbox) = sandbox {
+		sandbox.plugin_env(lua, &env, dir)?;
+		sandbox.reset(lua)?;
+	}
+
+	let data = fs::read(path)?;
+	let result = lua
+		.load(data)
+		.set_mode(ChunkMode::Text)
+		.set_name(chunkname)
+		.set_environment(env)
+		.into_function()
+		.and_then(|chunk| {
+			lua.registry_value::<Function>(&funcs.load_plugin)?
+				.call::<()>((name, chunk))
+		});
+
+	if let Some(sandbox) = sandbox {
+		sandbox.clear(lua)?;
+	}
+
+	result
+}
+
+fn load_plugins(
+	lua: &mut Lua,
+	funcs: &Funcs,
+	sandbox: Option<&Rc<Sandbox>>,
+	filter: Option<&[String]>,
+) -> io::Result<()> {
+	let read_dir = match fs::read_dir("plugins") {
+		Ok(r) => r,
+		Err(e) if e.kind() == ErrorKind::NotFound => {
+			println!("The 'plugins' directory is missing, no plugins will be loaded.");
+			return Ok(());
+		}
+		Err(e) => return Err(e),
+	};
+
+	for file in read_dir {
+		let file = file?;
+
+		if file.file_type()?.is_dir() {
+			if let Some(filter) = filter
+				&& !filter.iter().any(|name| file.file_name() == name.as_str())
+			{
+				continue;
+			}
+
+			let path = file.path();
+
+			if !path.join("init.lua").is_file() {
+				eprintln!("{:?} is missing an init.lua and will not be loaded.", path);
+				continue;
+			}
+
+			let name = file.file_name().to_string_lossy().into_owned();
+			if let Err(e) = load_plugin(lua, funcs, sandbox, &name, &path) {
+				eprintln!("Error while loading plugin {:?}, {}", path, e)
+			}
+		}
+	}
+
+	Ok(())
+}
+
+impl LuaApi {
+	pub fn new(
+		user_list: AwesomeCell<UserList>,
+		avatars: AwesomeCell<AvatarCatalog>,
+		config: AwesomeCell<BureauConfig>,
+		info: BureauInfo,
+	) -> mlua::Result<Self> {
+		let (lua, funcs, sandbox) = Self::create_state(user_list.clone(), &avatars, &config, info)?;
+
+		let lua_api = Self {
+			lua,
+			funcs,
+			sandbox,
+			user_list,
+			avatars,
+			config,
+			info,
+		};
+
+		lua_api.call::<_, ()>(&lua_api.funcs.plugins_loaded, ());
+
+		Ok(lua_api)
+	}
+
+	fn create_state(
+		user_list: AwesomeCell<UserList>,
+		avatars: &AwesomeCell<AvatarCatalog>,
+		config_cell: &AwesomeCell<BureauConfig>,
+		info: BureauInfo,
+	) -> mlua::Result<(Lua, Funcs, Option<Rc<Sandbox>>)> {
+		// Plugins can change the config while loading, don't keep it borrowed.
+		let config = config_cell.get().clone();
+
+		let (mut lua, sandbox) = match &config.sandbox {
+			Some(sandbox_config) => {
+				let (lua, sandbox) = Sandbox::create(sandbox_config.clone())?;
+				(lua, Some(Rc::new(sandbox)))
+			}
+			None => (unsafe { Lua::unsafe_new() }, None),
+		};
+
+		let config_tbl = lua.create_table()?;
+		if let Some(wrl) = &config.wrl {
+			config_tbl.set("wrl", wrl.as_str())?;
+		}
+		config_tbl.set("max_users", config.max_users)?;
+
+		let globals = lua.globals();
+		let package_loaded = globals.get::<Table>("package")?.get::<Table>("loaded")?;
+		package_loaded.set("config", config_tbl)?;
+		package_loaded.set("avatars", avatars::create_table(&lua, avatars)?)?;
+		package_loaded.set("packet", packet::create_table(&lua)?)?;
+		package_loaded.set("json", json::create_table(&lua)?)?;
+
+		let funcs = Funcs::init(
+			&mut lua,
+			user_list.clone(),
+			sandbox.as_ref(),
+			config_cell,
+			info,
+		)?;
+
+		let add_user = lua.registry_value::<Function>(&funcs.add_user)?;
+		for user in user_list.get().users.values() {
+			// Bots belong to the old plugins and are removed once the new ones have loaded.
+			if !user.initialized() || user.is_bot() {
+				continue;
+			}
+
+			let pos = user.pos();
+			add_user.call::<()>((
+				user.id(),
+				user.username().as_str(),
+				user.avatar().as_str(),
+				user.addr().ip().to_string(),
+				[pos.x, pos.y, pos.z],
+				user.rot().data,
+			))?;
+		}
+
+		load_plugins(
+			&mut lua,
+			&funcs,
+			sandbox.as_ref(),
+			config.plugins.as_deref(),
+		)?;
+
+		Ok((lua, funcs, sandbox))
+	}
+
+	/// Throw away the current Lua state and load every plugin again.
+	///
+	/// Users that are already connected are added to the new `users` table before plugins load,
+	/// without running `new_user` hooks. Bots aren't added. If the new state can't be created the old one is kept.
+	pub fn reload(&mut self) -> mlua::Result<()> {
+		let (lua, funcs, sandbox) = Self::create_state(
+			self.user_list.clone(),
+			&self.avatars,
+			&self.config,
+			self.info,
+		)?;
+
+		self.lua = lua;
+		self.funcs = funcs;
+		self.sandbox = sandbox;
+
+		self.call::<_, ()>(&self.funcs.plugins_loaded, ());
+
+		Ok(())
+	}
+
+	fn call<A, R>(&self, rk: &RegistryKey, args: A) -> Option<R>
+	where
+		A: IntoLuaMulti,
+		R: FromLuaMulti,
+	{
+		let f = self.lua.registry_value::<Function>(rk).ok()?;
+
+		match self.limited(|| f.call::<R>(args)) {
+			Ok(r) => Some(r),
+			Err(e) => {
+				eprintln!("Lua Error: {}", e);
+				None
+			}
+		}
+	}
+
+	/// Run `f` with the sandbox's limits, which are removed again once it returns.
+	fn limited<R>(&self, f: impl FnOnce() -> mlua::Result<R>) -> mlua::Result<R> {
+		if let Some(sandbox) = &self.sandbox {
+			sandbox.reset(&self.lua)?;
+		}
+
+		let result = f();
+
+		if let Some(sandbox) = &self.sandbox {
+			let _ = sandbox.clear(&self.lua);
+		}
+
+		result
+	}
+
+	pub fn think(&mut self) {
+		let _ = self.call::<_, ()>(&self.funcs.think, ());
+		let _ = self.call::<_, ()>(&self.funcs.timers, ());
+
+		if let Ok(resume) = self.lua.registry_value::<Function>(&self.funcs.resume_task) {
+			Tasks::resume(&self.funcs.tasks, |args| self.limited(|| resume.call(args)));
+		}
+	}
+
+	pub fn user_connect(&self, addr: SocketAddr) -> bool {
+		self.call::<_, Option<bool>>(&self.funcs.user_connect, addr.ip().to_string())
+			.flatten()
+			.unwrap_or(true)
+	}
+
+	pub fn new_user(&self, id: i32, name: &str, avatar: &str, ip: IpAddr) {
+		self.call::<_, ()>(&self.funcs.new_user, (id, name, avatar, ip.to_string()));
+	}
+
+	pub fn pos_update(&self, id: i32, pos: &Vector3) {
+		let _ = self.call::<_, ()>(&self.funcs.pos_update, (id, pos.x, pos.y, pos.z));
+	}
+
+	pub fn trans_update(&self, id: i32, rot: &Mat3) {
+		let _ = self.call::<_, ()>(&self.funcs.trans_update, (id, rot.data));
+	}
+
+	/// Returns true if a plugin accepts the update anyway.
+	pub fn movement_violation(&self, id: i32, pos: &Vector3, reason: &str) -> bool {
+		self.call::<_, Option<bool>>(
+			&self.funcs.movement_violation,
+			(id, pos.x, pos.y, pos.z, reason),
+		)
+		.flatten()
+		.unwrap_or(false)
+	}
+
+	pub fn chat_send(&self, id: i32, msg: &str) -> Option<String> {
+		self.call::<_, Option<String>>(&self.funcs.chat_send, (id, msg))?
+	}
+
+	pub fn validate_name(
+		&self,
+		id: i32,
+		ip: IpAddr,
+		name: &str,
+		suggested: &str,
+		reason: Option<&str>,
+	) -> Option<String> {
+		self.call::<_, Option<String>>(
+			&self.funcs.validate_name,
+			(id, ip.to_string(), name, suggested, reason),
+		)?
+	}
+
+	pub fn name_change(&self, id: i32, name: &str) -> Option<String> {
+		self.call::<_, Option<String>>(&self.funcs.name_change, (id, name))?
+	}
+
+	pub fn avatar_change(&self, id: i32, avatar: &str) -> Option<String> {
+		self.call::<_, Option<String>>(&self.funcs.avatar_change, (id, avatar))?
+	}
+
+	pub fn character_update(&self, id: i32, data: &str) {
+		let _ = self.call::<_, ()>(&self.funcs.character_update, (id, data));
+	}
+
+	pub fn private_chat(&self, id1: i32, id2: i32, msg: &str) -> Option<String> {
+		self.call::<_, Option<String>>(&self.funcs.private_chat, (id1, id2, msg))?
+	}
+
+	/// Check if any plugin registered a raw_packet hook.
+	pub fn wants_raw_packets(&self) -> bool {
+		self.funcs.raw_packets.get()
+	}
+
+	/// Run raw_packet hooks, returns the bytes to decode instead, empty to drop the packet.
+	pub fn raw_packet(&self, id: i32, section_type: u8, bytes: &[u8]) -> Option<Vec<u8>> {
+		let bytes = self.lua.create_string(bytes).ok()?;
+
+		self.call::<_, Option<mlua::String>>(&self.funcs.raw_packet, (id, section_type, bytes))?
+			.map(|bytes| bytes.as_bytes().to_vec())
+	}
+
+	pub fn user_disconnect(&self, id: i32) {
+		let _ = self.call::<_, Option<String>>(&self.funcs.user_disconnect, id);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{path::PathBuf, time::SystemTime};
+
+	use super::*;
+	use crate::bureau::{MovementRules, NameCharset, NameRules, Storage};
+
+	fn sandboxed_api(memory_limit: usize) -> LuaApi {
+		let config = BureauConfig {
+			connect_timeout: 0,
+			max_users: 16,
+			max_queue: 0,
+			aura_radius: 0.0,
+			aura_leave_radius: None,
+			aura_dwell: 0,
+			max_aura_changes: 0,
+			max_update_rate: 0.0,
+			update_falloff: 0.0,
+			plugins: Some(Vec::new()),
+			sandbox: Some(SandboxConfig {
+				instruction_limit: 10_000_000,
+				memory_limit,
+			}),
+			motd: None,
+			avatars: None,
+			default_avatar: String::new(),
+			names: NameRules {
+				min_length: 1,
+				max_length: 32,
+				charset: NameCharset::Printable,
+				unique: true,
+				reserved: Vec::new(),
+			},
+			movement: MovementRules {
+				bounds: None,
+				max_speed: None,
+			},
+			count_bots: false,
+			storage: Storage::new(PathBuf::new()),
+			wrl: None,
+		};
+
+		LuaApi::new(
+			AwesomeCell::new(UserList::new(config.max_users)),
+			AwesomeCell::new(AvatarCatalog::new(String::new())),
+			AwesomeCell::new(config),
+			BureauInfo {
+				port: 0,
+				started: SystemTime::now(),
+			},
+		)
+		.unwrap()
+	}
+
+	fn load(api: &LuaApi, name: &str, code: &str) {
+		let chunk = api.lua.load(code).into_function().unwrap();
+		api.lua
+			.registry_value::<Function>(&api.funcs.load_plugin)
+			.unwrap()
+			.call::<()>((name, chunk))
+			.unwrap();
+	}
+
+	#[test]
+	fn hooks_after_a_memory_error_still_run() {
+		let api = sandboxed_api(64 * 1024);
+
+		load(
+			&api,
+			"greedy",
+			r#"require("hook").plugins_loaded(function()
+				local t = {}
+				for i = 1, 1000000 do
+					t[i] = tostring(i)
+				end
+			end)"#,
+		);
+		load(
+			&api,
+			"counter",
+			r#"require("hook").plugins_loaded(function()
+				runs = (runs or 0) + 1
+			end)"#,
+		);
+
+		for _ in 0..10 {
+			api.call::<_, ()>(&api.funcs.plugins_loaded, ());
+		}
+
+		assert_eq!(api.lua.globals().get::<i32>("runs").unwrap(), 10);
+
+		let enabled = api
+			.lua
+			.load(r#"require("plugins").enabled"#)
+			.eval::<Function>()
+			.unwrap();
+		assert!(!enabled.call::<bool>("greedy").unwrap());
+	}
+}
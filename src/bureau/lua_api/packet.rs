@@ -0,0 +1,189 @@
+use std::mem;
+
+use mlua::{AnyUserData, Lua, MetaMethod, Table, UserData, UserDataMethods, Value};
+
+use crate::bureau::protocol::{
+	ByteWriter, MAX_CONTENT_SIZE, MAX_PACKET_SIZE, MsgCommon, Opcode, Strategy,
+};
+
+/// Run a consuming ByteWriter function that writes `len` bytes on a ByteWriter owned by Lua.
+///
+/// Writers live outside of Lua's memory limit, so they can't grow past `MAX_PACKET_SIZE`.
+fn write<F>(ud: &AnyUserData, len: usize, f: F) -> mlua::Result<()>
+where
+	F: FnOnce(ByteWriter) -> ByteWriter,
+{
+	let mut writer = ud.borrow_mut::<ByteWriter>()?;
+	if writer.bytes.len() + len > MAX_PACKET_SIZE {
+		return Err(mlua::Error::external(format!(
+			"packets can't be longer than {} bytes",
+			MAX_PACKET_SIZE
+		)));
+	}
+
+	let taken = mem::replace(&mut *writer, ByteWriter::new(0));
+	*writer = f(taken);
+
+	Ok(())
+}
+
+/// Get the bytes of packet content passed as either a ByteWriter or a string.
+fn content(value: &Value) -> mlua::Result<Vec<u8>> {
+	let bytes = match value {
+		Value::UserData(ud) => ud.borrow::<ByteWriter>()?.bytes.clone(),
+		Value::String(s) => s.as_bytes().to_vec(),
+		Value::Nil => Vec::new(),
+		_ => return Err(mlua::Error::external("expected a ByteWriter or string")),
+	};
+
+	if bytes.len() > MAX_CONTENT_SIZE {
+		return Err(mlua::Error::external(format!(
+			"packet content can't be longer than {} bytes",
+			MAX_CONTENT_SIZE
+		)));
+	}
+
+	Ok(bytes)
+}
+
+impl UserData for ByteWriter {
+	fn add_methods<M: UserDataMethods<Self>>(methods: &mut M) {
+		// Writes return the ByteWriter so calls can be chained.
+		methods.add_function("write_u8", |_, (ud, n): (AnyUserData, u8)| {
+			write(&ud, 1, |w| w.write_u8(n))?;
+			Ok(ud)
+		});
+
+		methods.add_function("write_i32", |_, (ud, n): (AnyUserData, i32)| {
+			write(&ud, 4, |w| w.write_i32(n))?;
+			Ok(ud)
+		});
+
+		methods.add_function("write_u32", |_, (ud, n): (AnyUserData, u32)| {
+			write(&ud, 4, |w| w.write_u32(n))?;
+			Ok(ud)
+		});
+
+		methods.add_function("write_f32", |_, (ud, n): (AnyUserData, f32)| {
+			write(&ud, 4, |w| w.write_f32(n))?;
+			Ok(ud)
+		});
+
+		methods.add_function("write_string", |_, (ud, s): (AnyUserData, mlua::String)| {
+			// Same as ByteWriter::write_string, without requiring valid UTF-8.
+			write(&ud, s.as_bytes().len() + 1, |w| {
+				w.write_arr(&s.as_bytes()).write_u8(0)
+			})?;
+			Ok(ud)
+		});
+
+		methods.add_function("write_bytes", |_, (ud, s): (AnyUserData, mlua::String)| {
+			write(&ud, s.as_bytes().len(), |w| w.write_arr(&s.as_bytes()))?;
+			Ok(ud)
+		});
+
+		methods.add_method("bytes", |lua, this, ()| lua.create_string(&this.bytes));
+
+		methods.add_meta_method(MetaMethod::Len, |_, this, ()| Ok(this.bytes.len()));
+	}
+}
+
+fn constants(lua: &Lua, values: &[(&str, u32)]) -> mlua::Result<Table> {
+	let tbl = lua.create_table()?;
+	for (name, value) in values {
+		tbl.set(*name, *value)?;
+	}
+
+	Ok(tbl)
+}
+
+/// Create the `packet` module.
+pub fn create_table(lua: &Lua) -> mlua::Result<Table> {
+	let tbl = lua.create_table()?;
+
+	tbl.set(
+		"writer",
+		lua.create_function(|_, ()| Ok(ByteWriter::new(0)))?,
+	)?;
+
+	tbl.set(
+		"general_message",
+		lua.create_function(|_, (id1, id2, opcode, data): (i32, i32, u32, Value)| {
+			Ok(ByteWriter::general_message(
+				id1,
+				id2,
+				opcode,
+				&content(&data)?,
+			))
+		})?,
+	)?;
+
+	tbl.set(
+		"message_common",
+		lua.create_function(
+			|_, (id1, id2, msg_type, strategy, data): (i32, i32, u32, u8, Value)| {
+				Ok(ByteWriter::message_common(
+					id1,
+					id2,
+					msg_type,
+					strategy,
+					&content(&data)?,
+				))
+			},
+		)?,
+	)?;
+
+	tbl.set(
+		"Opcode",
+		constants(
+			lua,
+			&[
+				("SMsgClientId", Opcode::SMsgClientId.into()),
+				("SMsgUserJoined", Opcode::SMsgUserJoined.into()),
+				("SMsgUserLeft", Opcode::SMsgUserLeft.into()),
+				("SMsgBroadcastId", Opcode::SMsgBroadcastId.into()),
+				("MsgCommon", Opcode::MsgCommon.into()),
+				("SMsgSetMaster", Opcode::SMsgSetMaster.into()),
+				("SMsgUserCount", Opcode::SMsgUserCount.into()),
+			],
+		)?,
+	)?;
+
+	tbl.set(
+		"MsgCommon",
+		constants(
+			lua,
+			&[
+				("TransformUpdate", MsgCommon::TransformUpdate.into()),
+				("ChatSend", MsgCommon::ChatSend.into()),
+				("CharacterUpdate", MsgCommon::CharacterUpdate.into()),
+				("NameChange", MsgCommon::NameChange.into()),
+				("AvatarChange", MsgCommon::AvatarChange.into()),
+				("PrivateChat", MsgCommon::PrivateChat.into()),
+				("ApplSpecific", MsgCommon::ApplSpecific.into()),
+			],
+		)?,
+	)?;
+
+	tbl.set(
+		"Strategy",
+		constants(
+			lua,
+			&[
+				("AuraClients", u8::from(Strategy::AuraClients).into()),
+				(
+					"AuraClientsExceptSender",
+					u8::from(Strategy::AuraClientsExceptSender).into(),
+				),
+				("SpecificClient", u8::from(Strategy::SpecificClient).into()),
+				("AllClients", u8::from(Strategy::AllClients).into()),
+				(
+					"AllClientsExceptSender",
+					u8::from(Strategy::AllClientsExceptSender).into(),
+				),
+			],
+		)?,
+	)?;
+
+	Ok(tbl)
+}
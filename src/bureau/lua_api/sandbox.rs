@@ -0,0 +1,119 @@
+use std::{cell::Cell, path::Path, rc::Rc};
+
+use mlua::{Function, HookTriggers, Lua, LuaOptions, RegistryKey, StdLib, Table, VmState};
+
+/// Limits applied to plugins when running with `--sandbox`.
+#[derive(Clone, PartialEq)]
+pub struct SandboxConfig {
+	/// Max number of Lua instructions a single hook call can run.
+	pub instruction_limit: u32,
+	/// Max number of bytes a single hook call can allocate.
+	pub memory_limit: usize,
+}
+
+/// How many instructions run between checks of the instruction limit.
+const INSTRUCTION_STEP: u32 = 1000;
+
+pub struct Sandbox {
+	config: SandboxConfig,
+	instructions: Rc<Cell<u32>>,
+	/// Set between `reset` and `clear`, instructions are only counted while limits apply.
+	active: Rc<Cell<bool>>,
+	plugin_env: RegistryKey,
+	raw_pcall: RegistryKey,
+}
+
+impl Sandbox {
+	/// Create a Lua state with a restricted standard library.
+	pub fn create(config: SandboxConfig) -> mlua::Result<(Lua, Self)> {
+		let lua = Lua::new_with(StdLib::ALL_SAFE, LuaOptions::new())?;
+
+		let instructions = Rc::new(Cell::new(0u32));
+		let active = Rc::new(Cell::new(false));
+
+		let limit_exceeded = lua.create_function({
+			let instructions = instructions.clone();
+			let active = active.clone();
+			let limit = config.instruction_limit;
+			move |_, ()| Ok(active.get() && instructions.get() > limit)
+		})?;
+
+		let (plugin_env, raw_pcall): (Function, Function) = lua
+			.load(include_str!("../lua/sandbox.lua"))
+			.call(limit_exceeded)?;
+		// Thread hooks don't run in coroutines created from Lua, the global hook does.
+		lua.set_global_hook(
+			HookTriggers::new().every_nth_instruction(INSTRUCTION_STEP),
+			{
+				let instructions = instructions.clone();
+				let active = active.clone();
+				let limit = config.instruction_limit;
+				move |_, _| {
+					if !active.get() {
+						return Ok(VmState::Continue);
+					}
+
+					let count = instructions.get().saturating_add(INSTRUCTION_STEP);
+					instructions.set(count);
+
+					if count > limit {
+						return Err(mlua::Error::runtime("instruction limit exceeded"));
+					}
+
+					Ok(VmState::Continue)
+				}
+			},
+		)?;
+
+		let sandbox = Self {
+			config,
+			instructions,
+			active,
+			plugin_env: lua.create_registry_value(plugin_env)?,
+			raw_pcall: lua.create_registry_value(raw_pcall)?,
+		};
+
+		Ok((lua, sandbox))
+	}
+
+	/// Give a plugin's environment access to files inside of `dir`.
+	pub fn plugin_env(&self, lua: &Lua, env: &Table, dir: &Path) -> mlua::Result<()> {
+		lua.registry_value::<Function>(&self.plugin_env)?
+			.call::<()>((env, dir.to_string_lossy()))
+	}
+
+	/// The `pcall` plugins had before it was replaced, it can catch instruction limit errors.
+	pub fn raw_pcall(&self, lua: &Lua) -> mlua::Result<Function> {
+		lua.registry_value(&self.raw_pcall)
+	}
+
+	/// Reset the instruction count and memory limit, called before every hook.
+	pub fn reset(&self, lua: &Lua) -> mlua::Result<()> {
+		self.instructions.set(0);
+		self.active.set(true);
+		lua.set_memory_limit(lua.used_memory() + self.config.memory_limit)?;
+
+		Ok(())
+	}
+
+	/// Remove limits so the bureau itself can use the Lua state.
+	pub fn clear(&self, lua: &Lua) -> mlua::Result<()> {
+		self.active.set(false);
+		lua.set_memory_limit(0)?;
+
+		Ok(())
+	}
+
+	/// Lua function for hook.lua to reset limits between each hook.
+	pub fn reset_function(self: &Rc<Self>, lua: &Lua) -> mlua::Result<Function> {
+		let sandbox = self.clone();
+		lua.create_function(move |lua, ()| sandbox.reset(lua))
+	}
+
+	/// Lua function for hook.lua to remove limits after a hook errors, so reporting the error
+	/// doesn't run into the same limit.
+	pub fn clear_function(self: &Rc<Self>, lua: &Lua) -> mlua::Result<Function> {
+		let sandbox = self.clone();
+		lua.create_function(move |lua, ()| sandbox.clear(lua))
+	}
+}
@@ -0,0 +1,53 @@
+use mlua::{Lua, LuaSerdeExt, Table, Value};
+
+use super::json::to_lua_options;
+use crate::bureau::storage::Storage;
+
+/// Create the table of storage functions used by storage.lua, every function takes a namespace.
+pub fn create_table(lua: &Lua, storage: &Storage) -> mlua::Result<Table> {
+	let tbl = lua.create_table()?;
+
+	tbl.set(
+		"get",
+		lua.create_function({
+			let storage = storage.clone();
+			move |lua, (namespace, key): (String, String)| {
+				let value = storage
+					.get(&namespace, &key)
+					.map_err(mlua::Error::external)?;
+
+				match value {
+					Some(value) => lua.to_value_with(&value, to_lua_options()),
+					None => Ok(Value::Nil),
+				}
+			}
+		})?,
+	)?;
+
+	tbl.set(
+		"set",
+		lua.create_function({
+			let storage = storage.clone();
+			move |lua, (namespace, key, value): (String, String, Value)| {
+				let value = match value {
+					Value::Nil => None,
+					value => Some(lua.from_value::<serde_json::Value>(value)?),
+				};
+
+				storage
+					.set(&namespace, &key, value)
+					.map_err(mlua::Error::external)
+			}
+		})?,
+	)?;
+
+	tbl.set(
+		"keys",
+		lua.create_function({
+			let storage = storage.clone();
+			move |_, namespace: String| storage.keys(&namespace).map_err(mlua::Error::external)
+		})?,
+	)?;
+
+	Ok(tbl)
+}
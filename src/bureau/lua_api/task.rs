@@ -0,0 +1,144 @@
+use std::time::{Duration, Instant};
+
+use mlua::{Function, Lua, MultiValue, Table, Thread, ThreadStatus, Value, Variadic};
+
+use crate::bureau::user_list::AwesomeCell;
+
+enum Wake {
+	/// Resume on the next think with these values.
+	Ready(MultiValue),
+	/// Resume once the time has passed.
+	At(Instant),
+	/// Resume when the event is notified, or with nothing once the timeout has passed.
+	Event(String, Option<Instant>),
+}
+
+struct Task {
+	id: u32,
+	thread: Thread,
+	plugin: Option<String>,
+	wake: Wake,
+}
+
+/// Coroutines spawned with `task.spawn`, resumed from `LuaApi::think`.
+#[derive(Default)]
+pub struct Tasks {
+	tasks: Vec<Task>,
+	next_id: u32,
+}
+
+fn secs(value: &Value) -> Option<Duration> {
+	let secs = value.as_number()?;
+	Duration::try_from_secs_f64(secs).ok()
+}
+
+#[allow(unused_mut)]
+impl Tasks {
+	/// Create the table of functions task.lua uses to talk to the scheduler.
+	pub fn create_table(tasks: &AwesomeCell<Tasks>, lua: &Lua) -> mlua::Result<Table> {
+		let tbl = lua.create_table()?;
+
+		tbl.set(
+			"add",
+			lua.create_function({
+				let tasks = tasks.clone();
+				move |_, (thread, plugin, args): (Thread, Option<String>, MultiValue)| {
+					let mut tasks = tasks.get_mut();
+
+					tasks.next_id += 1;
+					let id = tasks.next_id;
+
+					tasks.tasks.push(Task {
+						id,
+						thread,
+						plugin,
+						wake: Wake::Ready(args),
+					});
+
+					Ok(id)
+				}
+			})?,
+		)?;
+
+		tbl.set("notify", Self::notify_function(tasks, lua)?)?;
+
+		Ok(tbl)
+	}
+
+	/// Lua function that wakes every task waiting for an event.
+	pub fn notify_function(tasks: &AwesomeCell<Tasks>, lua: &Lua) -> mlua::Result<Function> {
+		let tasks = tasks.clone();
+		lua.create_function(move |_, (event, args): (String, Variadic<Value>)| {
+			for task in tasks.get_mut().tasks.iter_mut() {
+				if matches!(&task.wake, Wake::Event(name, _) if *name == event) {
+					task.wake = Wake::Ready(args.iter().cloned().collect());
+				}
+			}
+
+			Ok(())
+		})
+	}
+
+	/// Resume every task that is ready, `resume` calls the function returned by task.lua.
+	pub fn resume<F>(tasks: &AwesomeCell<Tasks>, mut resume: F)
+	where
+		F: FnMut(
+			(u32, Option<&str>, &Thread, MultiValue),
+		) -> mlua::Result<(bool, Option<String>, Value, Value)>,
+	{
+		let now = Instant::now();
+
+		// Take ready tasks out of the list so tasks can be spawned or notified while resuming.
+		let ready = {
+			let mut tasks = tasks.get_mut();
+			let (ready, waiting) = tasks
+				.tasks
+				.drain(..)
+				.partition::<Vec<_>, _>(|task| match &task.wake {
+					Wake::Ready(_) => true,
+					Wake::At(time) | Wake::Event(_, Some(time)) => *time <= now,
+					Wake::Event(_, None) => false,
+				});
+			tasks.tasks = waiting;
+
+			ready
+		};
+
+		for mut task in ready {
+			let args = match task.wake {
+				Wake::Ready(args) => args,
+				_ => MultiValue::new(),
+			};
+
+			let result = resume((task.id, task.plugin.as_deref(), &task.thread, args));
+
+			let (kind, a, b) = match result {
+				Ok((true, kind, a, b)) => (kind, a, b),
+				Ok((false, ..)) => continue, // Errored, already reported by plugin_call.
+				Err(e) => {
+					eprintln!("Lua Error: {}", e);
+					continue;
+				}
+			};
+
+			if task.thread.status() != ThreadStatus::Resumable {
+				continue;
+			}
+
+			task.wake = match kind.as_deref() {
+				Some("sleep") => Wake::At(now + secs(&a).unwrap_or_default()),
+				Some("event") => match a.as_string() {
+					Some(event) => Wake::Event(
+						event.to_string_lossy(),
+						secs(&b).map(|timeout| now + timeout),
+					),
+					None => Wake::Ready(MultiValue::new()),
+				},
+				// Plain coroutine.yield, resume on the next think.
+				_ => Wake::Ready(MultiValue::new()),
+			};
+
+			tasks.get_mut().tasks.push(task);
+		}
+	}
+}
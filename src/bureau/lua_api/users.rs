@@ -0,0 +1,362 @@
+use std::time::UNIX_EPOCH;
+
+use mlua::{Function, IntoLuaMulti, Lua, Table};
+
+use crate::bureau::{
+	math::{Mat3, Vector3},
+	protocol::ByteWriter,
+	user::User,
+	user_list::{AwesomeCell, UserList},
+};
+
+/// Create a Lua function that takes a user id and reads a value from that user.
+#[allow(clippy::needless_borrow)]
+fn getter<R, F>(lua: &Lua, user_list: &AwesomeCell<UserList>, f: F) -> mlua::Result<Function>
+where
+	R: IntoLuaMulti,
+	F: Fn(&UserList, &User) -> R + 'static,
+{
+	let user_list = user_list.clone();
+	lua.create_function(move |_, id: i32| {
+		let ul = user_list.get();
+		let Some(user) = ul.users.get(&id) else {
+			return Err(mlua::Error::external("invalid user"));
+		};
+
+		Ok(f(&ul, user))
+	})
+}
+
+/// Create a Lua function that takes a user id and a string, applies it to the user and sends
+/// the packet built by `packet` to the user and their aura.
+#[allow(unused_mut)]
+fn setter<F, P>(
+	lua: &Lua,
+	user_list: &AwesomeCell<UserList>,
+	apply: F,
+	packet: P,
+) -> mlua::Result<Function>
+where
+	F: Fn(&mut User, String) + 'static,
+	P: Fn(i32, &str) -> ByteWriter + 'static,
+{
+	let user_list = user_list.clone();
+	lua.create_function(move |_, (id, value): (i32, String)| {
+		let mut ul = user_list.get_mut();
+		let Some(user) = ul.users.get_mut(&id) else {
+			return Err(mlua::Error::external("invalid user"));
+		};
+
+		let bytes = packet(id, &value).bytes;
+		apply(user, value);
+
+		user.send(&bytes);
+		ul.send_aura(id, &bytes);
+
+		Ok(())
+	})
+}
+
+/// Get the id and chat line for a message sent as user `from`, `None` for system messages.
+///
+/// System messages are sent with each receiver's own id and no name in front, the same way
+/// `User::send_msg` sends them.
+fn sender(ul: &UserList, msg: &str, from: Option<i32>) -> mlua::Result<Option<(i32, String)>> {
+	let Some(from) = from else {
+		return Ok(None);
+	};
+
+	let Some(user) = ul.users.get(&from) else {
+		return Err(mlua::Error::external("invalid sender"));
+	};
+
+	Ok(Some((from, format!("{}: {}", user.username(), msg))))
+}
+
+/// Create the table of user functions used by users.lua.
+#[allow(unused_mut)]
+#[allow(clippy::needless_borrow)]
+pub fn create_table(lua: &Lua, user_list: &AwesomeCell<UserList>) -> mlua::Result<Table> {
+	let tbl = lua.create_table()?;
+
+	tbl.set(
+		"set_pos",
+		lua.create_function({
+			let user_list = user_list.clone();
+			move |_, (id, x, y, z): (i32, f32, f32, f32)| {
+				let mut ul = user_list.get_mut();
+				let Some(user) = ul.users.get_mut(&id) else {
+					return Err(mlua::Error::external("invalid user"));
+				};
+
+				user.set_pos(Vector3::new(x, y, z));
+				if user.is_bot() {
+					ul.bot_moved(id);
+				} else {
+					ul.aura_changed(id);
+				}
+
+				Ok(())
+			}
+		})?,
+	)?;
+
+	tbl.set(
+		"set_rot",
+		lua.create_function({
+			let user_list = user_list.clone();
+			move |_, (id, arr): (i32, [f32; 9])| {
+				let mut ul = user_list.get_mut();
+				let Some(user) = ul.users.get_mut(&id) else {
+					return Err(mlua::Error::external("invalid user"));
+				};
+
+				user.set_rot(Mat3 { data: arr });
+				if user.is_bot() {
+					ul.bot_moved(id);
+				}
+
+				Ok(())
+			}
+		})?,
+	)?;
+
+	tbl.set(
+		"send_msg",
+		lua.create_function({
+			let user_list = user_list.clone();
+			move |_, (id, msg, from): (i32, String, Option<i32>)| {
+				let mut ul = user_list.get_mut();
+				let from = sender(&ul, &msg, from)?;
+				let Some(user) = ul.users.get_mut(&id) else {
+					return Err(mlua::Error::external("invalid user"));
+				};
+
+				match from {
+					Some((from, text)) => user.send(&ByteWriter::chat_send(from, &text).bytes),
+					None => user.send_msg(&msg),
+				}
+
+				Ok(())
+			}
+		})?,
+	)?;
+
+	tbl.set(
+		"send_aura_msg",
+		lua.create_function({
+			let user_list = user_list.clone();
+			move |_, (id, msg, from): (i32, String, Option<i32>)| {
+				let mut ul = user_list.get_mut();
+				if !ul.users.contains_key(&id) {
+					return Err(mlua::Error::external("invalid user"));
+				}
+
+				match sender(&ul, &msg, from)? {
+					Some((from, text)) => {
+						ul.send_aura(id, &ByteWriter::chat_send(from, &text).bytes)
+					}
+					None => ul.for_aura(id, |_, other| other.send_msg(&msg)),
+				}
+
+				Ok(())
+			}
+		})?,
+	)?;
+
+	tbl.set(
+		"broadcast",
+		lua.create_function({
+			let user_list = user_list.clone();
+			move |_, (msg, from): (String, Option<i32>)| {
+				let mut ul = user_list.get_mut();
+
+				match sender(&ul, &msg, from)? {
+					Some((from, text)) => ul.send_all(&ByteWriter::chat_send(from, &text).bytes),
+					None => {
+						for user in ul.users.values_mut() {
+							user.send_msg(&msg);
+						}
+					}
+				}
+
+				Ok(())
+			}
+		})?,
+	)?;
+
+	tbl.set(
+		"send_packet",
+		lua.create_function({
+			let user_list = user_list.clone();
+			move |_, (id, msg): (i32, mlua::String)| {
+				let mut ul = user_list.get_mut();
+				let Some(user) = ul.users.get_mut(&id) else {
+					return Err(mlua::Error::external("invalid user"));
+				};
+
+				user.send(&msg.as_bytes());
+				Ok(())
+			}
+		})?,
+	)?;
+
+	tbl.set(
+		"disconnect",
+		lua.create_function({
+			let user_list = user_list.clone();
+			move |_, id: i32| {
+				let mut ul = user_list.get_mut();
+				let Some(user) = ul.users.get_mut(&id) else {
+					return Err(mlua::Error::external("invalid user"));
+				};
+
+				user.disconnect();
+				Ok(())
+			}
+		})?,
+	)?;
+
+	tbl.set(
+		"aura",
+		getter(lua, user_list, |_, user| {
+			user.aura().iter().copied().collect::<Vec<_>>()
+		})?,
+	)?;
+
+	tbl.set(
+		"character_data",
+		getter(lua, user_list, |_, user| user.data().clone())?,
+	)?;
+
+	tbl.set(
+		"connected_at",
+		getter(lua, user_list, |_, user| {
+			user.connected_at()
+				.duration_since(UNIX_EPOCH)
+				.unwrap_or_default()
+				.as_secs_f64()
+		})?,
+	)?;
+
+	tbl.set(
+		"is_master",
+		getter(lua, user_list, |ul, user| ul.is_master(user.id()))?,
+	)?;
+
+	tbl.set(
+		"addr",
+		getter(lua, user_list, |_, user| {
+			(!user.is_bot()).then(|| user.addr().to_string())
+		})?,
+	)?;
+
+	tbl.set("is_bot", getter(lua, user_list, |_, user| user.is_bot())?)?;
+
+	tbl.set(
+		"aura_radius",
+		getter(lua, user_list, |_, user| user.aura_radius())?,
+	)?;
+
+	tbl.set(
+		"set_aura_radius",
+		lua.create_function({
+			let user_list = user_list.clone();
+			move |_, (id, radius): (i32, Option<f32>)| {
+				if radius.is_some_and(|radius| !radius.is_finite() || radius < 0.0) {
+					return Err(mlua::Error::external("aura radius can't be negative"));
+				}
+
+				let mut ul = user_list.get_mut();
+				if !ul.users.contains_key(&id) {
+					return Err(mlua::Error::external("invalid user"));
+				}
+
+				ul.set_aura_radius(id, radius);
+
+				Ok(())
+			}
+		})?,
+	)?;
+
+	tbl.set("hidden", getter(lua, user_list, |_, user| user.hidden())?)?;
+
+	tbl.set(
+		"set_hidden",
+		lua.create_function({
+			let user_list = user_list.clone();
+			move |_, (id, hidden): (i32, bool)| {
+				let mut ul = user_list.get_mut();
+				let Some(user) = ul.users.get_mut(&id) else {
+					return Err(mlua::Error::external("invalid user"));
+				};
+
+				user.set_hidden(hidden);
+				ul.aura_changed(id);
+
+				Ok(())
+			}
+		})?,
+	)?;
+
+	tbl.set(
+		"aura_override",
+		lua.create_function({
+			let user_list = user_list.clone();
+			move |_, (id, other): (i32, i32)| Ok(user_list.get().aura_override(id, other))
+		})?,
+	)?;
+
+	tbl.set(
+		"set_aura_override",
+		lua.create_function({
+			let user_list = user_list.clone();
+			move |_, (id, other, include): (i32, i32, Option<bool>)| {
+				let mut ul = user_list.get_mut();
+				if !ul.users.contains_key(&id) || !ul.users.contains_key(&other) {
+					return Err(mlua::Error::external("invalid user"));
+				}
+
+				if id == other {
+					return Err(mlua::Error::external("users are never in their own aura"));
+				}
+
+				ul.set_aura_override(id, other, include);
+
+				Ok(())
+			}
+		})?,
+	)?;
+
+	tbl.set(
+		"spawn_bot",
+		lua.create_function({
+			let user_list = user_list.clone();
+			move |_, (name, avatar, data): (String, String, String)| {
+				Ok(user_list.get_mut().add_bot(name, avatar, data))
+			}
+		})?,
+	)?;
+
+	tbl.set(
+		"initialized",
+		getter(lua, user_list, |_, user| user.initialized())?,
+	)?;
+
+	tbl.set(
+		"set_name",
+		setter(lua, user_list, User::set_username, ByteWriter::name_change)?,
+	)?;
+
+	tbl.set(
+		"set_avatar",
+		setter(lua, user_list, User::set_avatar, ByteWriter::avatar_change)?,
+	)?;
+
+	tbl.set(
+		"set_character_data",
+		setter(lua, user_list, User::set_data, ByteWriter::character_update)?,
+	)?;
+
+	Ok(tbl)
+}
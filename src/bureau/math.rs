@@ -65,3 +65,97 @@ impl Mat3 {
 		Mat3 { data: [0.0; 9] }
 	}
 }
+
+/// Fixed point number used for positions and rotations on the wire, 1.0 is 65535 (not 65536).
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Fixed16(pub i32);
+
+impl Fixed16 {
+	const SCALE: f64 = 65535.0;
+
+	/// Round to the nearest step, halfway cases away from zero. Values out of range saturate to
+	/// `i32::MIN` or `i32::MAX` and NaN becomes 0.
+	pub fn from_f32(n: f32) -> Fixed16 {
+		Fixed16((n as f64 * Self::SCALE).round() as i32)
+	}
+
+	/// Convert to the nearest f32. Values under 256 survive `from_f32(to_f32())` exactly, larger
+	/// ones lose precision to f32 once and then stay the same on every round trip.
+	pub fn to_f32(self) -> f32 {
+		(self.0 as f64 / Self::SCALE) as f32
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// Largest raw value that survives a round trip exactly, about 256.0.
+	const EXACT: i32 = 16_776_960;
+
+	#[test]
+	fn round_trip_is_exact_below_256() {
+		for n in (-EXACT..=EXACT).step_by(7).chain([-EXACT, EXACT]) {
+			assert_eq!(Fixed16::from_f32(Fixed16(n).to_f32()), Fixed16(n));
+		}
+	}
+
+	#[test]
+	fn round_trip_is_stable_over_the_whole_range() {
+		let samples =
+			(i32::MIN..=i32::MAX)
+				.step_by(9973)
+				.chain([i32::MIN, i32::MAX, EXACT + 1, -EXACT - 1]);
+
+		for n in samples {
+			let f = Fixed16(n).to_f32();
+			let once = Fixed16::from_f32(f);
+
+			assert_eq!(once.to_f32(), f, "{} moved after one round trip", n);
+			assert_eq!(Fixed16::from_f32(once.to_f32()), once);
+		}
+	}
+
+	#[test]
+	fn rounds_to_the_nearest_step() {
+		let step = 1.0 / 65535.0;
+
+		assert_eq!(Fixed16::from_f32(0.6 * step), Fixed16(1));
+		assert_eq!(Fixed16::from_f32(0.4 * step), Fixed16(0));
+		assert_eq!(Fixed16::from_f32(1.6 * step), Fixed16(2));
+		assert_eq!(Fixed16::from_f32(-0.6 * step), Fixed16(-1));
+		assert_eq!(Fixed16::from_f32(-1.4 * step), Fixed16(-1));
+		assert_eq!(Fixed16::from_f32(1.0), Fixed16(65535));
+
+		// Exactly halfway, 0.5 * 65535 = 32767.5.
+		assert_eq!(Fixed16::from_f32(0.5), Fixed16(32768));
+		assert_eq!(Fixed16::from_f32(-0.5), Fixed16(-32768));
+	}
+
+	#[test]
+	fn saturates_out_of_range_values() {
+		assert_eq!(Fixed16::from_f32(32769.0), Fixed16(i32::MAX));
+		assert_eq!(Fixed16::from_f32(-32769.0), Fixed16(i32::MIN));
+		assert_eq!(Fixed16::from_f32(1e30), Fixed16(i32::MAX));
+		assert_eq!(Fixed16::from_f32(-1e30), Fixed16(i32::MIN));
+		assert_eq!(Fixed16::from_f32(f32::MAX), Fixed16(i32::MAX));
+		assert_eq!(Fixed16::from_f32(f32::MIN), Fixed16(i32::MIN));
+
+		assert_eq!(
+			Fixed16::from_f32(Fixed16(i32::MAX).to_f32()),
+			Fixed16(i32::MAX)
+		);
+		assert_eq!(
+			Fixed16::from_f32(Fixed16(i32::MIN).to_f32()),
+			Fixed16(i32::MIN)
+		);
+	}
+
+	#[test]
+	fn nan_and_infinity() {
+		assert_eq!(Fixed16::from_f32(f32::NAN), Fixed16(0));
+		assert_eq!(Fixed16::from_f32(-f32::NAN), Fixed16(0));
+		assert_eq!(Fixed16::from_f32(f32::INFINITY), Fixed16(i32::MAX));
+		assert_eq!(Fixed16::from_f32(f32::NEG_INFINITY), Fixed16(i32::MIN));
+	}
+}
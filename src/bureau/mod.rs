@@ -1,21 +1,46 @@
-use std::{io, net::ToSocketAddrs, thread, time::Duration};
-
+use std::{
+	io,
+	net::ToSocketAddrs,
+	path::PathBuf,
+	sync::{
+		Arc,
+		atomic::{AtomicBool, Ordering},
+	},
+	thread,
+	time::{Duration, SystemTime},
+};
+
+use avatars::AvatarCatalog;
+use fanout::{Movement, UpdateRate};
 use listener::{Listener, ListenerEvent};
-use lua_api::LuaApi;
+use lua_api::{BureauInfo, LuaApi};
 use math::{Mat3, Vector3};
+use movement::Violation;
+use names::NameProblem;
 use protocol::{ByteWriter, MsgCommon, Strategy};
-use user::UserEvent;
-use user_list::{AwesomeCell, UserList};
+use signal_hook::consts::{SIGHUP, SIGUSR1};
+use user::{User, UserEvent};
+use user_list::{AuraSettings, AwesomeCell, UserList};
 
+mod avatars;
 mod error;
+mod fanout;
+mod grid;
 mod listener;
 mod lua_api;
 mod math;
+mod movement;
+mod names;
 mod protocol;
+mod storage;
 mod user;
 mod user_list;
 
 pub use error::*;
+pub use lua_api::SandboxConfig;
+pub use movement::{Bounds, MovementRules};
+pub use names::{NameCharset, NameRules};
+pub use storage::Storage;
 
 #[derive(Clone)]
 pub struct BureauConfig {
@@ -23,14 +48,69 @@ pub struct BureauConfig {
 	pub max_users: i32,
 	pub max_queue: usize,
 	pub aura_radius: f32,
+	/// Distance users have to move away before they leave an aura, `aura_radius` when unset.
+	pub aura_leave_radius: Option<f32>,
+	/// Milliseconds a pair of users stays in or out of each other's aura before it can change.
+	pub aura_dwell: u64,
+	/// Max aura changes per tick, the rest are made on later ticks. No limit when 0.
+	pub max_aura_changes: usize,
+	/// Max movement updates per second forwarded from one user to another. No limit when 0.
+	pub max_update_rate: f32,
+	/// Distance after which movement is forwarded less often, see `UpdateRate`. Off when 0.
+	pub update_falloff: f32,
+	pub plugins: Option<Vec<String>>,
+	pub sandbox: Option<SandboxConfig>,
+	pub motd: Option<String>,
+	/// Avatar list file or directory, any `avtwrl/` avatar is allowed when unset.
+	pub avatars: Option<PathBuf>,
+	pub default_avatar: String,
+	pub names: NameRules,
+	/// World bounds and max speed movement updates are checked against.
+	pub movement: MovementRules,
+	/// Include bots spawned by plugins in the user count sent to clients.
+	pub count_bots: bool,
+	/// Plugin storage, shared by every bureau created from clones of this config.
+	pub storage: Storage,
 	pub wrl: Option<String>,
 }
 
+impl BureauConfig {
+	fn update_rate(&self) -> UpdateRate {
+		UpdateRate {
+			max_rate: self.max_update_rate,
+			falloff: self.update_falloff,
+		}
+	}
+
+	fn aura_settings(&self) -> AuraSettings {
+		AuraSettings {
+			radius: self.aura_radius,
+			leave_margin: self.aura_leave_radius.map_or(0.0, |leave_radius| {
+				(leave_radius - self.aura_radius).max(0.0)
+			}),
+			dwell: Duration::from_millis(self.aura_dwell),
+		}
+	}
+
+	fn avatar_catalog(&self) -> io::Result<AvatarCatalog> {
+		let default = self.default_avatar.clone();
+
+		match &self.avatars {
+			Some(path) => AvatarCatalog::load(path, default),
+			None => Ok(AvatarCatalog::new(default)),
+		}
+	}
+}
+
 pub struct Bureau {
 	port: u16,
-	config: BureauConfig,
+	/// Shared with Lua, plugins can change some fields while running.
+	config: AwesomeCell<BureauConfig>,
+	/// Settings auras were last updated with, auras are updated again when the config changes.
+	aura: AuraSettings,
 	listener: Listener,
 	user_list: AwesomeCell<UserList>,
+	avatars: AwesomeCell<AvatarCatalog>,
 	lua_api: LuaApi,
 }
 
@@ -45,15 +125,31 @@ impl Bureau {
 		);
 
 		let user_list = AwesomeCell::new(UserList::new(config.max_users));
-		let lua_api = LuaApi::new(user_list.clone(), &config)?;
+		user_list.get_mut().set_count_bots(config.count_bots);
+		user_list.get_mut().set_grid_size(config.aura_radius);
+		let aura = config.aura_settings();
+		let avatars = AwesomeCell::new(config.avatar_catalog()?);
 		let listener = Listener::new(addr, config.connect_timeout, config.max_queue)?;
+		let port = listener.port();
+		let config = AwesomeCell::new(config);
+		let lua_api = LuaApi::new(
+			user_list.clone(),
+			avatars.clone(),
+			config.clone(),
+			BureauInfo {
+				port,
+				started: SystemTime::now(),
+			},
+		)?;
 
 		Ok(Self {
-			port: listener.port(),
+			port,
 			listener,
 			config,
+			aura,
 
 			user_list,
+			avatars,
 
 			lua_api,
 		})
@@ -63,22 +159,164 @@ impl Bureau {
 		self.port
 	}
 
-	pub fn config(&self) -> &BureauConfig {
-		&self.config
+	/// Whether a client can join, bots take ids from the same `max_users` slots as clients.
+	pub fn has_room(&self) -> bool {
+		self.user_list.get().users.len() < self.config.get().max_users as usize
+	}
+
+	/// Apply a new config to the running bureau, changes that can't be applied live are logged.
+	pub fn reconfigure(&mut self, config: BureauConfig) {
+		assert!(
+			config.max_users > 0,
+			"max_users config option wasn't positive ({})",
+			config.max_users
+		);
+
+		let name = config.wrl.as_deref().unwrap_or("bureau");
+		let mut current = self.config.get_mut();
+
+		if config.connect_timeout != current.connect_timeout
+			|| config.max_queue != current.max_queue
+		{
+			println!(
+				"'{}' (port {}): connect_timeout and max_queue can't be changed while running.",
+				name, self.port
+			);
+		}
+
+		let mut user_list = self.user_list.get_mut();
+		user_list.set_max_users(config.max_users);
+		user_list.set_count_bots(config.count_bots);
+		drop(user_list);
+
+		// Always reload the catalog so edits to the list or directory are picked up.
+		match config.avatar_catalog() {
+			Ok(avatars) => *self.avatars.get_mut() = avatars,
+			Err(e) => eprintln!(
+				"'{}' (port {}): failed to load avatars, keeping the old ones, {}",
+				name, self.port, e
+			),
+		}
+
+		let reload_plugins = config.plugins != current.plugins || config.sandbox != current.sandbox;
+
+		*current = BureauConfig {
+			connect_timeout: current.connect_timeout,
+			max_queue: current.max_queue,
+			..config
+		};
+		drop(current);
+
+		if reload_plugins {
+			self.reload_plugins();
+		}
+
+		self.refresh_auras();
+	}
+
+	/// Update every aura if the aura settings changed since the last time they were updated.
+	fn refresh_auras(&mut self) {
+		let aura = self.config.get().aura_settings();
+		if aura == self.aura {
+			return;
+		}
+		self.user_list.get_mut().set_grid_size(aura.radius);
+		self.aura = aura;
+
+		let ids = self
+			.user_list
+			.get()
+			.users
+			.values()
+			.filter(|user| user.initialized())
+			.map(|user| user.id())
+			.collect::<Vec<_>>();
+
+		for id in ids {
+			self.update_aura(id);
+		}
+	}
+
+	/// Reload every plugin, errors are logged and the old plugins are kept.
+	///
+	/// Bots spawned by the old plugins are removed once the new plugins are loaded, they'll
+	/// spawn them again.
+	pub fn reload_plugins(&mut self) {
+		let bots = self
+			.user_list
+			.get()
+			.users
+			.values()
+			.filter(|user| user.is_bot())
+			.map(|user| user.id())
+			.collect::<Vec<_>>();
+
+		if let Err(e) = self.lua_api.reload() {
+			eprintln!(
+				"'{}' (port {}): failed to reload plugins, {}",
+				self.config.get().wrl.as_deref().unwrap_or("bureau"),
+				self.port,
+				e
+			);
+			return;
+		}
+
+		if !bots.is_empty() {
+			let mut user_list = self.user_list.get_mut();
+			for id in bots {
+				user_list.disconnect(id);
+			}
+
+			user_list.send_user_count();
+		}
 	}
 
+	/// Number of connected users, not counting bots.
 	pub fn user_count(&self) -> usize {
-		self.user_list.get().users.len()
+		self.user_list
+			.get()
+			.users
+			.values()
+			.filter(|user| !user.is_bot())
+			.count()
 	}
 
 	pub fn run(&mut self) -> ! {
+		let reload_requested = Arc::new(AtomicBool::new(false));
+		signal_hook::flag::register(SIGUSR1, reload_requested.clone())
+			.expect("registering SIGUSR1 handler");
+
+		// Without a handler SIGHUP would kill the bureau.
+		let reconfigure_requested = Arc::new(AtomicBool::new(false));
+		signal_hook::flag::register(SIGHUP, reconfigure_requested.clone())
+			.expect("registering SIGHUP handler");
+
 		loop {
+			if reload_requested.swap(false, Ordering::Relaxed) {
+				println!("Reloading plugins.");
+				self.reload_plugins();
+			}
+
+			if reconfigure_requested.swap(false, Ordering::Relaxed) {
+				println!(
+					"Reloading avatars, other options come from the command line and can't be reloaded."
+				);
+				let config = self.config.get().clone();
+				self.reconfigure(config);
+			}
+
 			self.poll().expect("error during poll");
 			thread::sleep(Duration::from_millis(100));
 		}
 	}
 
 	pub fn poll(&mut self) -> io::Result<()> {
+		let max_aura_changes = self.config.get().max_aura_changes;
+		self.user_list.get_mut().new_aura_tick(
+			(max_aura_changes > 0).then_some(max_aura_changes),
+			self.aura.dwell,
+		);
+
 		if let Some(event) = self.listener.poll_event()? {
 			match event {
 				ListenerEvent::Incoming(addr) => {
@@ -94,6 +332,8 @@ impl Bureau {
 			}
 		}
 
+		let count = self.user_list.get().user_count();
+
 		let ids = self
 			.user_list
 			.get()
@@ -104,7 +344,24 @@ impl Bureau {
 		for id in ids.iter().copied() {
 			let mut user_list = self.user_list.get_mut();
 			let user = user_list.users.get_mut(&id).unwrap();
-			let Some(event) = user.poll() else {
+			let Some((section_type, mut bytes)) = user.read_packet() else {
+				continue;
+			};
+			drop(user_list);
+
+			if self.lua_api.wants_raw_packets() {
+				match self.lua_api.raw_packet(id, section_type, &bytes) {
+					Some(new_bytes) if new_bytes.is_empty() => continue,
+					Some(new_bytes) => bytes = new_bytes,
+					None => (),
+				}
+			}
+
+			let mut user_list = self.user_list.get_mut();
+			let Some(user) = user_list.users.get_mut(&id) else {
+				continue;
+			};
+			let Some(event) = user.decode(section_type, &bytes) else {
 				continue;
 			};
 			drop(user_list);
@@ -126,8 +383,16 @@ impl Bureau {
 		}
 
 		self.lua_api.think();
+		self.update_bots();
+		self.refresh_auras();
+		let stale = self.user_list.get_mut().take_stale_auras();
+		for id in stale {
+			self.update_aura(id);
+		}
 
-		let count = self.user_list.get().users.len();
+		let rate = self.config.get().update_rate();
+		self.user_list.get_mut().flush_movement(&rate);
+		self.config.get().storage.flush();
 
 		for id in ids {
 			let mut user_list = self.user_list.get_mut();
@@ -141,77 +406,160 @@ impl Bureau {
 		}
 
 		let mut user_list = self.user_list.get_mut();
-		if count != user_list.users.len() {
+		if count != user_list.user_count() {
 			user_list.send_user_count();
 		}
 
 		Ok(())
 	}
 
+	/// Update the aura of bots that plugins spawned or moved and send where they are.
+	fn update_bots(&mut self) {
+		let moved = self.user_list.get_mut().take_moved_bots();
+
+		for id in moved {
+			self.update_aura(id);
+
+			let mut user_list = self.user_list.get_mut();
+			let Some(bot) = user_list.users.get(&id) else {
+				continue;
+			};
+
+			let bytes = ByteWriter::transform_update(id, bot.rot(), bot.pos()).bytes;
+			user_list.send_aura(id, &bytes);
+		}
+	}
+
 	fn update_aura(&mut self, id: i32) {
-		let mut user_list = self.user_list.get_mut();
-		user_list.for_others(id, |user, other| {
-			if !other.initialized() {
-				return;
-			}
+		self.user_list.get_mut().update_aura(id, &self.aura);
+	}
 
-			let in_radius = user.pos().distance_sqr(other.pos()) <= self.config.aura_radius.powi(2);
-			let in_aura = user.aura().contains(&other.id());
+	/// Apply the name rules to a name `id` picked, plugins can override the result or reject the
+	/// name, which returns `None`.
+	fn validate_name(&self, id: i32, name: &str) -> Option<String> {
+		let (valid, problem, ip) = {
+			let user_list = self.user_list.get();
+			let (valid, problem) = self.config.get().names.validate(name, |name| {
+				let name = name.to_lowercase();
+				user_list.users.values().any(|user| {
+					user.id() != id && user.initialized() && user.username().to_lowercase() == name
+				})
+			});
+
+			(
+				valid,
+				problem,
+				user_list.users.get(&id).unwrap().addr().ip(),
+			)
+		};
 
-			if !in_aura && in_radius {
-				user.add_aura(other);
-				other.add_aura(user);
-			} else if in_aura && !in_radius {
-				user.remove_aura(other);
-				other.remove_aura(user);
-			}
-		});
+		match self
+			.lua_api
+			.validate_name(id, ip, name, &valid, problem.map(NameProblem::as_str))
+		{
+			Some(name) if name.is_empty() => None,
+			Some(name) => Some(name),
+			None => Some(valid),
+		}
 	}
 
 	fn new_user(&mut self, id: i32, username: String, avatar: String) {
+		let Some(username) = self.validate_name(id, &username) else {
+			// Rejected by a plugin, the user never joins.
+			if let Some(user) = self.user_list.get_mut().users.get_mut(&id) {
+				user.disconnect();
+			}
+
+			return;
+		};
+		let avatar = self.avatars.get().validate(avatar);
+
 		let mut user_list = self.user_list.get_mut();
+		let user = user_list.users.get_mut(&id).unwrap();
+		user.welcome(username.clone(), avatar.clone());
+		user_list.aura_changed(id);
+
 		user_list.master();
 		user_list.send_user_count();
 
-		let ip = user_list.users.get_mut(&id).unwrap().addr().ip();
+		let user = user_list.users.get_mut(&id).unwrap();
+		if let Some(motd) = &self.config.get().motd {
+			user.send_msg(motd);
+		}
+
+		let ip = user.addr().ip();
 		drop(user_list);
 
 		self.lua_api.new_user(id, &username, &avatar, ip);
 	}
 
+	/// Check a movement update against the movement rules and apply it, updates that break them
+	/// are only applied if a plugin accepts them. Returns false if the update was dropped.
+	fn apply_movement(&mut self, id: i32, pos: &Vector3, rot: Option<&Mat3>) -> bool {
+		let violation = {
+			let user_list = self.user_list.get();
+			let Some(user) = user_list.users.get(&id) else {
+				return false;
+			};
+
+			self.config.get().movement.check(
+				user.pos(),
+				pos,
+				rot,
+				user.moved_at().map(|at| at.elapsed()),
+			)
+		};
+
+		if let Some(violation) = violation {
+			let accepted = self.lua_api.movement_violation(id, pos, violation.as_str());
+
+			// NaN would break every distance check after it, so plugins can't accept it.
+			if !accepted || matches!(violation, Violation::Invalid) {
+				return false;
+			}
+		}
+
+		let mut user_list = self.user_list.get_mut();
+		let Some(user) = user_list.users.get_mut(&id) else {
+			return false;
+		};
+
+		user.apply_movement(pos.clone(), rot.cloned());
+
+		true
+	}
+
 	fn position_update(&mut self, id: i32, pos: Vector3) {
+		if !self.apply_movement(id, &pos, None) {
+			return;
+		}
+
 		self.update_aura(id);
-		self.user_list
-			.get_mut()
-			.send_aura(id, &ByteWriter::position_update(id, &pos).bytes);
+
+		let rate = self.config.get().update_rate();
+		self.user_list.get_mut().send_movement(
+			id,
+			Movement::Position,
+			ByteWriter::position_update(id, &pos).bytes,
+			&rate,
+		);
 
 		self.lua_api.pos_update(id, &pos);
 	}
 
 	fn transform_update(&mut self, id: i32, rot: Mat3, pos: Vector3) {
-		self.update_aura(id);
-
-		let mut transform_update = ByteWriter::new(12 * 4);
-
-		for f in rot.data {
-			transform_update = transform_update.write_f32(f);
+		if !self.apply_movement(id, &pos, Some(&rot)) {
+			return;
 		}
 
-		transform_update = transform_update
-			.write_f32(pos.x)
-			.write_f32(pos.y)
-			.write_f32(pos.z);
+		self.update_aura(id);
 
-		self.user_list.get_mut().send_aura(
+		let rate = self.config.get().update_rate();
+		self.user_list.get_mut().send_movement(
 			id,
-			&ByteWriter::message_common(
-				id,
-				id,
-				MsgCommon::TransformUpdate,
-				Strategy::AuraClients,
-				&transform_update.bytes,
-			)
-			.bytes,
+			Movement::Transform,
+			ByteWriter::transform_update(id, &rot, &pos).bytes,
+			&rate,
 		);
 
 		self.lua_api.trans_update(id, &rot);
@@ -230,65 +578,97 @@ impl Bureau {
 
 		let text = format!("{}: {}", user_list.users.get(&id).unwrap().username(), msg);
 
-		user_list.send_aura(
-			id,
-			&ByteWriter::message_common(
-				id,
-				id,
-				MsgCommon::ChatSend,
-				Strategy::AllClientsExceptSender,
-				&ByteWriter::new(text.len() + 1).write_string(&text).bytes,
-			)
-			.bytes,
-		);
+		user_list.send_aura(id, &ByteWriter::chat_send(id, &text).bytes);
 	}
 
 	fn character_update(&mut self, id: i32, data: String) {
-		self.user_list.get_mut().send_aura(
-			id,
-			&ByteWriter::message_common(
-				id,
-				id,
-				MsgCommon::CharacterUpdate,
-				Strategy::AuraClientsExceptSender,
-				&ByteWriter::new(data.len() + 1).write_string(&data).bytes,
-			)
-			.bytes,
-		);
+		self.user_list
+			.get_mut()
+			.send_aura(id, &ByteWriter::character_update(id, &data).bytes);
+
+		self.lua_api.character_update(id, &data);
 	}
 
 	fn name_change(&mut self, id: i32, name: String) {
-		self.user_list.get_mut().send_aura(
+		let (valid, hook_result) = match self.validate_name(id, &name) {
+			Some(valid) => {
+				// Tell the client if the rules changed its name, unless a hook rewrites it anyway.
+				let hook_result = self
+					.lua_api
+					.name_change(id, &valid)
+					.or_else(|| (valid != name).then(|| valid.clone()));
+
+				(valid, hook_result)
+			}
+			// Rejected by a plugin, the user keeps their old name.
+			None => (name, Some(String::new())),
+		};
+
+		self.apply_change(
 			id,
-			&ByteWriter::message_common(
-				id,
-				id,
-				MsgCommon::NameChange,
-				Strategy::AuraClientsExceptSender,
-				&ByteWriter::new(name.len() + 1).write_string(&name).bytes,
-			)
-			.bytes,
+			valid,
+			hook_result,
+			User::username,
+			User::set_username,
+			ByteWriter::name_change,
 		);
-
-		self.lua_api.name_change(id, &name);
 	}
 
 	fn avatar_change(&mut self, id: i32, avatar: String) {
-		self.user_list.get_mut().send_aura(
+		if !self.avatars.get().is_allowed(&avatar) {
+			if let Some(user) = self.user_list.get_mut().users.get_mut(&id) {
+				user.send(&ByteWriter::avatar_change(id, user.avatar()).bytes);
+			}
+
+			return;
+		}
+
+		let hook_result = self.lua_api.avatar_change(id, &avatar);
+
+		self.apply_change(
 			id,
-			&ByteWriter::message_common(
-				id,
-				id,
-				MsgCommon::AvatarChange,
-				Strategy::AuraClientsExceptSender,
-				&ByteWriter::new(avatar.len() + 1)
-					.write_string(&avatar)
-					.bytes,
-			)
-			.bytes,
+			avatar,
+			hook_result,
+			User::avatar,
+			User::set_avatar,
+			ByteWriter::avatar_change,
 		);
+	}
+
+	/// Apply a name or avatar change after the hooks ran.
+	///
+	/// An empty `hook_result` rejects the change and sends the current value back to the user,
+	/// any other value replaces the one the user sent.
+	fn apply_change(
+		&mut self,
+		id: i32,
+		value: String,
+		hook_result: Option<String>,
+		current: fn(&User) -> &String,
+		apply: fn(&mut User, String),
+		packet: fn(i32, &str) -> ByteWriter,
+	) {
+		let mut user_list = self.user_list.get_mut();
+		let Some(user) = user_list.users.get_mut(&id) else {
+			return;
+		};
+
+		let value = match hook_result {
+			Some(new_value) if new_value.is_empty() => {
+				user.send(&packet(id, current(user)).bytes);
+				return;
+			}
+			Some(new_value) => {
+				// The client shows what it sent, tell it what everyone else sees.
+				user.send(&packet(id, &new_value).bytes);
+				new_value
+			}
+			None => value,
+		};
 
-		self.lua_api.avatar_change(id, &avatar);
+		let bytes = packet(id, &value).bytes;
+		apply(user, value);
+		user_list.send_aura(id, &bytes);
 	}
 
 	fn private_chat(&mut self, id: i32, receiver: i32, mut text: String) {
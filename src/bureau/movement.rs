@@ -0,0 +1,103 @@
+use std::{str::FromStr, time::Duration};
+
+use super::math::{Mat3, Vector3};
+
+/// Shortest time speed is measured over, updates are read at most once per tick.
+const MIN_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Box users have to stay inside of.
+#[derive(Clone)]
+pub struct Bounds {
+	pub min: Vector3,
+	pub max: Vector3,
+}
+
+impl Bounds {
+	fn contains(&self, pos: &Vector3) -> bool {
+		(self.min.x..=self.max.x).contains(&pos.x)
+			&& (self.min.y..=self.max.y).contains(&pos.y)
+			&& (self.min.z..=self.max.z).contains(&pos.z)
+	}
+}
+
+impl FromStr for Bounds {
+	type Err = String;
+
+	/// Parse two corners as `x1,y1,z1,x2,y2,z2`.
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let n = s
+			.split(',')
+			.map(|n| n.trim().parse::<f32>())
+			.collect::<Result<Vec<_>, _>>()
+			.ok()
+			.filter(|n| n.len() == 6 && n.iter().all(|n| n.is_finite()))
+			.ok_or_else(|| format!("invalid bounds '{}', expected x1,y1,z1,x2,y2,z2", s))?;
+
+		Ok(Self {
+			min: Vector3::new(n[0].min(n[3]), n[1].min(n[4]), n[2].min(n[5])),
+			max: Vector3::new(n[0].max(n[3]), n[1].max(n[4]), n[2].max(n[5])),
+		})
+	}
+}
+
+/// Why a movement update was rejected by `MovementRules::check`.
+#[derive(Clone, Copy)]
+pub enum Violation {
+	/// NaN or infinite position or rotation.
+	Invalid,
+	/// Outside of the world bounds.
+	OutOfBounds,
+	/// Moved faster than the max speed since the last update.
+	TooFast,
+}
+
+impl Violation {
+	pub fn as_str(self) -> &'static str {
+		match self {
+			Self::Invalid => "invalid",
+			Self::OutOfBounds => "out_of_bounds",
+			Self::TooFast => "too_fast",
+		}
+	}
+}
+
+#[derive(Clone)]
+pub struct MovementRules {
+	/// Box positions have to be in, anywhere is allowed when `None`.
+	pub bounds: Option<Bounds>,
+	/// Max distance per second between updates, no limit when `None`.
+	pub max_speed: Option<f32>,
+}
+
+impl MovementRules {
+	/// Check a move from `from` to `to`, `elapsed` is the time since the last accepted update
+	/// or `None` for a user's first update, which can be anywhere inside the bounds.
+	pub fn check(
+		&self,
+		from: &Vector3,
+		to: &Vector3,
+		rot: Option<&Mat3>,
+		elapsed: Option<Duration>,
+	) -> Option<Violation> {
+		let finite = [to.x, to.y, to.z].iter().all(|n| n.is_finite())
+			&& rot.is_none_or(|rot| rot.data.iter().all(|n| n.is_finite()));
+		if !finite {
+			return Some(Violation::Invalid);
+		}
+
+		if let Some(bounds) = &self.bounds
+			&& !bounds.contains(to)
+		{
+			return Some(Violation::OutOfBounds);
+		}
+
+		if let (Some(max_speed), Some(elapsed)) = (self.max_speed, elapsed) {
+			let max_distance = max_speed * elapsed.max(MIN_INTERVAL).as_secs_f32();
+			if from.distance_sqr(to) > max_distance.powi(2) {
+				return Some(Violation::TooFast);
+			}
+		}
+
+		None
+	}
+}
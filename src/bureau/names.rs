@@ -0,0 +1,158 @@
+use std::str::FromStr;
+
+/// Name given to users whose name has no allowed characters left.
+const FALLBACK_NAME: &str = "Guest";
+
+/// Characters allowed in usernames.
+///
+/// `:` is never allowed, chat messages are sent as `name: message` and a name containing `: `
+/// would break private chat.
+#[derive(Clone, Copy, PartialEq)]
+pub enum NameCharset {
+	/// Anything that isn't a control character.
+	Printable,
+	/// Printable ASCII.
+	Ascii,
+	/// ASCII letters, digits, spaces, `_`, `-` and `.`.
+	Alphanumeric,
+}
+
+impl NameCharset {
+	pub fn as_str(self) -> &'static str {
+		match self {
+			Self::Printable => "printable",
+			Self::Ascii => "ascii",
+			Self::Alphanumeric => "alphanumeric",
+		}
+	}
+
+	fn allows(self, c: char) -> bool {
+		if c == ':' {
+			return false;
+		}
+
+		match self {
+			Self::Printable => !c.is_control(),
+			Self::Ascii => c.is_ascii() && !c.is_ascii_control(),
+			Self::Alphanumeric => c.is_ascii_alphanumeric() || matches!(c, ' ' | '_' | '-' | '.'),
+		}
+	}
+}
+
+impl FromStr for NameCharset {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"printable" => Ok(Self::Printable),
+			"ascii" => Ok(Self::Ascii),
+			"alphanumeric" => Ok(Self::Alphanumeric),
+			_ => Err(format!(
+				"unknown charset '{}', expected printable, ascii or alphanumeric",
+				s
+			)),
+		}
+	}
+}
+
+/// Why a name was changed by `NameRules::validate`.
+#[derive(Clone, Copy)]
+pub enum NameProblem {
+	/// Too short, too long or had characters that aren't allowed.
+	Invalid,
+	/// Matched a reserved name.
+	Reserved,
+	/// Another user already has the name.
+	Taken,
+}
+
+impl NameProblem {
+	pub fn as_str(self) -> &'static str {
+		match self {
+			Self::Invalid => "invalid",
+			Self::Reserved => "reserved",
+			Self::Taken => "taken",
+		}
+	}
+}
+
+#[derive(Clone)]
+pub struct NameRules {
+	pub min_length: usize,
+	pub max_length: usize,
+	pub charset: NameCharset,
+	/// Give users a numbered name when theirs is already taken.
+	pub unique: bool,
+	/// Names only plugins can give out, compared case insensitively.
+	pub reserved: Vec<String>,
+}
+
+impl NameRules {
+	fn is_reserved(&self, name: &str) -> bool {
+		self.reserved
+			.iter()
+			.any(|reserved| reserved.to_lowercase() == name.to_lowercase())
+	}
+
+	/// Get a name that follows the rules, `is_taken` checks if another user has a name.
+	///
+	/// Names with characters that aren't allowed have them removed and are cut to `max_length`,
+	/// reserved or taken names get a number added to the end. Returns the problem with the
+	/// original name if it had to be changed.
+	pub fn validate<F>(&self, name: &str, is_taken: F) -> (String, Option<NameProblem>)
+	where
+		F: Fn(&str) -> bool,
+	{
+		let mut problem = None;
+
+		let mut valid = truncate(
+			name.chars()
+				.filter(|c| self.charset.allows(*c))
+				.collect::<String>()
+				.trim(),
+			self.max_length,
+		);
+
+		if valid.chars().count() < self.min_length {
+			valid = truncate(FALLBACK_NAME, self.max_length);
+		}
+
+		if valid != name {
+			problem = Some(NameProblem::Invalid);
+		}
+
+		let unavailable = |name: &str| {
+			if self.is_reserved(name) {
+				Some(NameProblem::Reserved)
+			} else if self.unique && is_taken(name) {
+				Some(NameProblem::Taken)
+			} else {
+				None
+			}
+		};
+
+		let Some(unavailable_problem) = unavailable(&valid) else {
+			return (valid, problem);
+		};
+		let problem = problem.unwrap_or(unavailable_problem);
+
+		for n in 2.. {
+			let suffix = n.to_string();
+			let candidate = format!(
+				"{}{}",
+				truncate(&valid, self.max_length.saturating_sub(suffix.len())).trim_end(),
+				suffix
+			);
+
+			if unavailable(&candidate).is_none() {
+				return (candidate, Some(problem));
+			}
+		}
+
+		unreachable!()
+	}
+}
+
+fn truncate(s: &str, max: usize) -> String {
+	s.chars().take(max).collect()
+}
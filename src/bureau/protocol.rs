@@ -1,7 +1,12 @@
-use super::math::Vector3;
+use super::math::{Fixed16, Mat3, Vector3};
 
 // Documentation of types listed here should be found in VSCP.md.
 
+/// Largest general message content the bureau reads, clients sending more are disconnected.
+pub const MAX_CONTENT_SIZE: usize = 1024;
+/// Largest packet, a general message header and `MAX_CONTENT_SIZE` bytes of content.
+pub const MAX_PACKET_SIZE: usize = 17 + MAX_CONTENT_SIZE;
+
 #[repr(u32)]
 pub enum Opcode {
 	// CMsgNewUser = 0,
@@ -42,6 +47,24 @@ pub enum Strategy {
 	Invalid = u8::MAX,
 }
 
+impl From<Opcode> for u32 {
+	fn from(value: Opcode) -> Self {
+		value as u32
+	}
+}
+
+impl From<MsgCommon> for u32 {
+	fn from(value: MsgCommon) -> Self {
+		value as u32
+	}
+}
+
+impl From<Strategy> for u8 {
+	fn from(value: Strategy) -> Self {
+		value as u8
+	}
+}
+
 impl From<u8> for Strategy {
 	fn from(value: u8) -> Self {
 		match value {
@@ -80,15 +103,9 @@ impl ByteReader for [u8] {
 		String::from_utf8(buf[..i].to_vec()).unwrap_or_default()
 	}
 
+	/// Read a `Fixed16`.
 	fn read_f32(&self, start: usize) -> f32 {
-		let n = i32::from_be_bytes([
-			self[start],
-			self[start + 1],
-			self[start + 2],
-			self[start + 3],
-		]);
-
-		(n as f32) / 65535.0
+		Fixed16(self.read_i32(start)).to_f32()
 	}
 
 	fn read_u32(&self, start: usize) -> u32 {
@@ -123,12 +140,12 @@ impl ByteWriter {
 		}
 	}
 
-	pub fn general_message(id1: i32, id2: i32, opcode: Opcode, content: &[u8]) -> Self {
+	pub fn general_message(id1: i32, id2: i32, opcode: impl Into<u32>, content: &[u8]) -> Self {
 		Self::new(17 + content.len())
 			.write_u8(0)
 			.write_i32(id1)
 			.write_i32(id2)
-			.write_u32(opcode as u32)
+			.write_u32(opcode.into())
 			.write_u32(content.len() as u32)
 			.write_arr(content)
 	}
@@ -147,11 +164,77 @@ impl ByteWriter {
 			.write_u8(0)
 	}
 
+	/// TransformUpdate from `id` to its aura.
+	pub fn transform_update(id: i32, rot: &Mat3, pos: &Vector3) -> Self {
+		let mut transform_update = Self::new(12 * 4);
+
+		for f in rot.data {
+			transform_update = transform_update.write_f32(f);
+		}
+
+		transform_update = transform_update
+			.write_f32(pos.x)
+			.write_f32(pos.y)
+			.write_f32(pos.z);
+
+		Self::message_common(
+			id,
+			id,
+			MsgCommon::TransformUpdate,
+			Strategy::AuraClients,
+			&transform_update.bytes,
+		)
+	}
+
+	/// ChatSend from `id`, `text` is shown as is so it should start with the sender's name.
+	pub fn chat_send(id: i32, text: &str) -> Self {
+		Self::message_common(
+			id,
+			id,
+			MsgCommon::ChatSend,
+			Strategy::AllClientsExceptSender,
+			&Self::new(text.len() + 1).write_string(text).bytes,
+		)
+	}
+
+	/// NameChange sent from `id` to its aura.
+	pub fn name_change(id: i32, name: &str) -> Self {
+		Self::message_common(
+			id,
+			id,
+			MsgCommon::NameChange,
+			Strategy::AuraClientsExceptSender,
+			&Self::new(name.len() + 1).write_string(name).bytes,
+		)
+	}
+
+	/// AvatarChange sent from `id` to its aura.
+	pub fn avatar_change(id: i32, avatar: &str) -> Self {
+		Self::message_common(
+			id,
+			id,
+			MsgCommon::AvatarChange,
+			Strategy::AuraClientsExceptSender,
+			&Self::new(avatar.len() + 1).write_string(avatar).bytes,
+		)
+	}
+
+	/// CharacterUpdate sent from `id` to its aura.
+	pub fn character_update(id: i32, data: &str) -> Self {
+		Self::message_common(
+			id,
+			id,
+			MsgCommon::CharacterUpdate,
+			Strategy::AuraClientsExceptSender,
+			&Self::new(data.len() + 1).write_string(data).bytes,
+		)
+	}
+
 	pub fn message_common(
 		id1: i32,
 		id2: i32,
-		msg_type: MsgCommon,
-		strategy: Strategy,
+		msg_type: impl Into<u32>,
+		strategy: impl Into<u8>,
 		content: &[u8],
 	) -> Self {
 		Self::general_message(
@@ -160,15 +243,16 @@ impl ByteWriter {
 			Opcode::MsgCommon,
 			&Self::new(9 + content.len())
 				.write_i32(id2)
-				.write_u32(msg_type as u32)
-				.write_u8(strategy as u8)
+				.write_u32(msg_type.into())
+				.write_u8(strategy.into())
 				.write_arr(content)
 				.bytes,
 		)
 	}
 
+	/// Write `n` as a `Fixed16`.
 	pub fn write_f32(self, n: f32) -> Self {
-		self.write_i32((n * (0xFFFF as f32)) as i32)
+		self.write_i32(Fixed16::from_f32(n).0)
 	}
 
 	pub fn write_i32(mut self, n: i32) -> Self {
@@ -0,0 +1,162 @@
+use std::{
+	fs::{self, File},
+	io::{self, ErrorKind, Write},
+	path::PathBuf,
+};
+
+use hashbrown::HashMap;
+use serde_json::{Map, Value};
+
+use super::user_list::AwesomeCell;
+
+struct Namespace {
+	values: Map<String, Value>,
+	dirty: bool,
+	/// Set while saving fails, so the error is only logged once.
+	save_failed: bool,
+}
+
+struct Inner {
+	dir: PathBuf,
+	namespaces: HashMap<String, Namespace>,
+}
+
+/// Key-value storage for plugins, every namespace is saved to its own JSON file in `dir`.
+///
+/// Clones share the same data so every bureau in a process sees the same values.
+#[derive(Clone)]
+pub struct Storage {
+	inner: AwesomeCell<Inner>,
+}
+
+impl Inner {
+	fn path(&self, namespace: &str) -> PathBuf {
+		self.dir.join(format!("{}.json", namespace))
+	}
+
+	/// Get a namespace, loading it from disk the first time it's used.
+	fn namespace(&mut self, namespace: &str) -> io::Result<&mut Namespace> {
+		if namespace.is_empty()
+			|| namespace.starts_with('.')
+			|| namespace.contains(['/', '\\', ':', '\0'])
+		{
+			return Err(io::Error::new(
+				ErrorKind::InvalidInput,
+				format!("invalid namespace '{}'", namespace),
+			));
+		}
+
+		if !self.namespaces.contains_key(namespace) {
+			let path = self.path(namespace);
+			let values = match fs::read(&path) {
+				Ok(data) => serde_json::from_slice(&data).map_err(|e| {
+					io::Error::new(ErrorKind::InvalidData, format!("{:?}: {}", path, e))
+				})?,
+				Err(e) if e.kind() == ErrorKind::NotFound => Map::new(),
+				Err(e) => return Err(e),
+			};
+
+			self.namespaces.insert(
+				namespace.to_string(),
+				Namespace {
+					values,
+					dirty: false,
+					save_failed: false,
+				},
+			);
+		}
+
+		Ok(self.namespaces.get_mut(namespace).unwrap())
+	}
+
+	/// Write a namespace to a temporary file and move it over the old one, so a crash while
+	/// saving never leaves a half written file.
+	fn save(&self, namespace: &str, values: &Map<String, Value>) -> io::Result<()> {
+		fs::create_dir_all(&self.dir)?;
+
+		let path = self.path(namespace);
+		let tmp = self.dir.join(format!("{}.json.tmp", namespace));
+
+		let mut file = File::create(&tmp)?;
+		file.write_all(&serde_json::to_vec_pretty(values)?)?;
+		file.sync_all()?;
+
+		fs::rename(tmp, path)
+	}
+}
+
+#[allow(unused_mut)]
+impl Storage {
+	pub fn new(dir: PathBuf) -> Self {
+		Self {
+			inner: AwesomeCell::new(Inner {
+				dir,
+				namespaces: HashMap::new(),
+			}),
+		}
+	}
+
+	/// Directory namespaces are saved in.
+	pub fn dir(&self) -> PathBuf {
+		self.inner.get().dir.clone()
+	}
+
+	pub fn get(&self, namespace: &str, key: &str) -> io::Result<Option<Value>> {
+		let mut inner = self.inner.get_mut();
+		Ok(inner.namespace(namespace)?.values.get(key).cloned())
+	}
+
+	/// Set a value, `None` removes it. Changes are written to disk by `flush`.
+	pub fn set(&self, namespace: &str, key: &str, value: Option<Value>) -> io::Result<()> {
+		let mut inner = self.inner.get_mut();
+		let namespace = inner.namespace(namespace)?;
+
+		match value {
+			Some(value) => {
+				namespace.values.insert(key.to_string(), value);
+			}
+			None => {
+				namespace.values.remove(key);
+			}
+		}
+		namespace.dirty = true;
+
+		Ok(())
+	}
+
+	pub fn keys(&self, namespace: &str) -> io::Result<Vec<String>> {
+		let mut inner = self.inner.get_mut();
+		Ok(inner.namespace(namespace)?.values.keys().cloned().collect())
+	}
+
+	/// Write every namespace that changed to disk, a namespace that fails to save stays dirty and
+	/// is tried again on the next flush.
+	pub fn flush(&self) {
+		let mut inner = self.inner.get_mut();
+
+		let dirty = inner
+			.namespaces
+			.iter()
+			.filter(|(_, namespace)| namespace.dirty)
+			.map(|(name, _)| name.clone())
+			.collect::<Vec<_>>();
+
+		for name in dirty {
+			let result = inner.save(&name, &inner.namespaces[&name].values);
+			let namespace = inner.namespaces.get_mut(&name).unwrap();
+
+			match result {
+				Ok(()) => {
+					namespace.dirty = false;
+					namespace.save_failed = false;
+				}
+				Err(e) => {
+					if !namespace.save_failed {
+						eprintln!("Failed to save storage '{}', retrying, {}", name, e);
+					}
+					namespace.save_failed = true;
+				}
+			}
+		}
+	}
+}
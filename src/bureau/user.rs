@@ -2,11 +2,12 @@ use std::{
 	collections::HashSet,
 	io::{self, ErrorKind, Read, Write},
 	net::{SocketAddr, TcpStream},
+	time::{Instant, SystemTime},
 };
 
 use super::{
 	math::{Mat3, Vector3},
-	protocol::{ByteReader, ByteWriter, MsgCommon, Opcode, Strategy},
+	protocol::{ByteReader, ByteWriter, MAX_CONTENT_SIZE, MsgCommon, Opcode, Strategy},
 };
 
 pub enum UserEvent {
@@ -22,22 +23,20 @@ pub enum UserEvent {
 	ApplSpecific(Strategy, i32, String, String, i32),
 }
 
-fn validate_avatar(avatar: String) -> String {
-	if !avatar.starts_with("avtwrl/") {
-		return String::from("avtwrl/01cat.wrl");
-	}
-
-	avatar
-}
-
 pub struct User {
-	socket: TcpStream,
+	/// `None` for bots spawned by plugins.
+	socket: Option<TcpStream>,
 	addr: SocketAddr,
 	connected: bool,
+	connected_at: SystemTime,
 
 	id: i32,
 	initialized: bool,
 	aura: HashSet<i32>,
+	/// Radius used instead of the config's, set by plugins.
+	aura_radius: Option<f32>,
+	/// Hidden users are left out of every aura unless a plugin includes them.
+	hidden: bool,
 
 	username: String,
 	avatar: String,
@@ -45,18 +44,23 @@ pub struct User {
 
 	position: Vector3,
 	rotation: Mat3,
+	/// When the last movement update from the client was accepted.
+	moved_at: Option<Instant>,
 }
 
 impl User {
 	pub fn new(id: i32, socket: TcpStream) -> io::Result<Self> {
 		Ok(Self {
 			addr: socket.peer_addr()?,
-			socket,
+			socket: Some(socket),
 			connected: true,
+			connected_at: SystemTime::now(),
 
 			id,
 			initialized: false,
 			aura: HashSet::new(),
+			aura_radius: None,
+			hidden: false,
 
 			username: String::new(),
 			avatar: String::new(),
@@ -64,10 +68,39 @@ impl User {
 
 			position: Vector3::new(0.0, 0.0, 0.0),
 			rotation: Mat3::new(),
+			moved_at: None,
 		})
 	}
 
-	/// Get user SocketAddr.
+	/// Create a bot, a user without a socket that plugins control.
+	pub fn new_bot(id: i32, username: String, avatar: String, data: String) -> Self {
+		Self {
+			addr: SocketAddr::from(([0, 0, 0, 0], 0)),
+			socket: None,
+			connected: true,
+			connected_at: SystemTime::now(),
+
+			id,
+			initialized: true,
+			aura: HashSet::new(),
+			aura_radius: None,
+			hidden: false,
+
+			username,
+			avatar,
+			data,
+
+			position: Vector3::new(0.0, 0.0, 0.0),
+			rotation: Mat3::new(),
+			moved_at: None,
+		}
+	}
+
+	pub fn is_bot(&self) -> bool {
+		self.socket.is_none()
+	}
+
+	/// Get user SocketAddr, bots have an unspecified address.
 	pub fn addr(&self) -> &SocketAddr {
 		&self.addr
 	}
@@ -76,6 +109,11 @@ impl User {
 		self.connected
 	}
 
+	/// Get the time the user connected at.
+	pub fn connected_at(&self) -> SystemTime {
+		self.connected_at
+	}
+
 	pub fn disconnect(&mut self) {
 		self.connected = false
 	}
@@ -92,6 +130,22 @@ impl User {
 		&self.aura
 	}
 
+	pub fn aura_radius(&self) -> Option<f32> {
+		self.aura_radius
+	}
+
+	pub fn set_aura_radius(&mut self, aura_radius: Option<f32>) {
+		self.aura_radius = aura_radius;
+	}
+
+	pub fn hidden(&self) -> bool {
+		self.hidden
+	}
+
+	pub fn set_hidden(&mut self, hidden: bool) {
+		self.hidden = hidden;
+	}
+
 	pub fn add_aura(&mut self, other: &User) {
 		self.aura.insert(other.id);
 		self.send(
@@ -118,6 +172,12 @@ impl User {
 			)
 			.bytes,
 		);
+		// Bots only move when a plugin moves them, send where they are now.
+		if other.is_bot() {
+			self.send(
+				&ByteWriter::transform_update(other.id, &other.rotation, &other.position).bytes,
+			);
+		}
 	}
 
 	pub fn remove_aura(&mut self, other: &User) {
@@ -137,6 +197,28 @@ impl User {
 		&self.username
 	}
 
+	pub fn set_username(&mut self, username: String) {
+		self.username = username;
+	}
+
+	pub fn avatar(&self) -> &String {
+		&self.avatar
+	}
+
+	pub fn set_avatar(&mut self, avatar: String) {
+		self.avatar = avatar;
+	}
+
+	/// Get user character data.
+	pub fn data(&self) -> &String {
+		&self.data
+	}
+
+	/// Set user character data.
+	pub fn set_data(&mut self, data: String) {
+		self.data = data;
+	}
+
 	/// Set user position.
 	pub fn set_pos(&mut self, pos: Vector3) {
 		self.send(&ByteWriter::position_update(self.id, &pos).bytes);
@@ -149,39 +231,88 @@ impl User {
 
 	/// Set user rotation.
 	pub fn set_rot(&mut self, rot: Mat3) {
-		let mut transform_update = ByteWriter::new(48);
+		self.send(&ByteWriter::transform_update(self.id, &rot, &self.position).bytes);
+		self.rotation = rot;
+	}
 
-		for f in rot.data {
-			transform_update = transform_update.write_f32(f);
+	/// Apply a movement update from the client, unlike `set_pos` nothing is sent back.
+	pub fn apply_movement(&mut self, pos: Vector3, rot: Option<Mat3>) {
+		self.position = pos;
+		if let Some(rot) = rot {
+			self.rotation = rot;
 		}
 
-		transform_update = transform_update
-			.write_f32(self.position.x)
-			.write_f32(self.position.y)
-			.write_f32(self.position.z);
+		self.moved_at = Some(Instant::now());
+	}
+
+	/// Get the time the last movement update from the client was applied at.
+	pub fn moved_at(&self) -> Option<Instant> {
+		self.moved_at
+	}
+
+	/// Get user rotation.
+	pub fn rot(&self) -> &Mat3 {
+		&self.rotation
+	}
+
+	/// Finish joining with a validated name and avatar, the user gets aura updates after this.
+	pub fn welcome(&mut self, username: String, avatar: String) {
+		self.username = username;
+		self.avatar = avatar;
 
 		self.send(
-			&ByteWriter::message_common(
+			&ByteWriter::general_message(0, self.id, Opcode::SMsgClientId, &self.id.to_be_bytes())
+				.bytes,
+		);
+
+		self.send(
+			&ByteWriter::general_message(
 				self.id,
 				self.id,
-				MsgCommon::TransformUpdate,
-				Strategy::AuraClients,
-				&transform_update.bytes,
+				Opcode::SMsgUserJoined,
+				&ByteWriter::new(8)
+					.write_i32(self.id)
+					.write_i32(self.id)
+					.write_string(&self.avatar)
+					.write_string(&self.username)
+					.bytes,
 			)
 			.bytes,
 		);
-		self.rotation = rot;
+
+		self.send(
+			&ByteWriter::general_message(
+				self.id,
+				self.id,
+				Opcode::SMsgBroadcastId,
+				&self.id.to_be_bytes(),
+			)
+			.bytes,
+		);
+
+		self.initialized = true; // ready for aura updates
+	}
+
+	/// Send a message to the User's chat.
+	pub fn send_msg(&mut self, msg: &str) {
+		self.send(&ByteWriter::chat_send(self.id, msg).bytes);
 	}
 
 	/// Send all data within a ByteWriter to the socket this User contains.
 	pub fn send(&mut self, buf: &[u8]) {
-		if self.socket.write_all(buf).is_err() {
+		let Some(socket) = &mut self.socket else {
+			return;
+		};
+
+		if socket.write_all(buf).is_err() {
 			self.connected = false;
 		}
 	}
 
 	fn read(&mut self, buf: &mut [u8]) -> Option<usize> {
-		match self.socket.read(buf) {
+		let socket = self.socket.as_mut()?;
+
+		match socket.read(buf) {
 			Ok(count) => {
 				if count == 0 {
 					self.connected = false;
@@ -198,56 +329,91 @@ impl User {
 		}
 	}
 
-	/// Poll a single event from this User.
-	pub fn poll(&mut self) -> Option<UserEvent> {
+	/// Read a single packet from this User, returns its section type and everything after it.
+	///
+	/// General messages are checked against the max size but aren't decoded.
+	pub fn read_packet(&mut self) -> Option<(u8, Vec<u8>)> {
 		let mut buf: [u8; 1] = [0];
 		let n = self.read(&mut buf)?;
 		if n == 0 {
 			return None;
 		}
 
-		let packet_type = buf[0];
+		let section_type = buf[0];
+
+		let bytes = match section_type {
+			0 => {
+				let mut msg_header: [u8; 16] = [0; 16];
+				let n = self.read(&mut msg_header)?;
+				if n < 16 {
+					return None;
+				}
+
+				let size = msg_header.read_u32(12);
+
+				// Would be a bad idea to dynamically allocate a number of bytes that could be u32::MAX.
+				if size as usize > MAX_CONTENT_SIZE {
+					self.connected = false;
+					return None;
+				}
 
-		match packet_type {
-			0 => self.general_message(),
+				let mut packet = vec![0; 16 + size as usize];
+				packet[..16].copy_from_slice(&msg_header);
+				let n = self.read(&mut packet[16..])?;
+				if n < size as usize {
+					return None;
+				}
+
+				packet
+			}
 			1 => {
 				// I don't know what this type does. I do know its most likely 14 bytes.
 				// To avoid outright disconnecting the user for sending a packet that should be valid,
-				// I'm just going to discard the next 14 bytes and hope it'll still work out.
-				self.read(&mut [0; 14]);
-				None
+				// I'm just going to read the next 14 bytes and hope it'll still work out.
+				let mut packet = vec![0; 14];
+				let n = self.read(&mut packet)?;
+				packet.truncate(n);
+				packet
+			}
+			2 => {
+				let mut packet = vec![0; 26];
+				let n = self.read(&mut packet)?;
+				if n < 26 {
+					return None;
+				}
+
+				packet
 			}
-			2 => self.position_update(),
 			_ => {
 				self.connected = false;
-				None
+				return None;
 			}
+		};
+
+		Some((section_type, bytes))
+	}
+
+	/// Decode a packet read by `read_packet`.
+	pub fn decode(&mut self, section_type: u8, bytes: &[u8]) -> Option<UserEvent> {
+		match section_type {
+			0 => self.general_message(bytes),
+			2 => self.position_update(bytes),
+			_ => None,
 		}
 	}
 
-	fn general_message(&mut self) -> Option<UserEvent> {
-		let mut msg_header: [u8; 16] = [0; 16];
-		let n = self.read(&mut msg_header)?;
-		if n < 16 {
+	fn general_message(&mut self, bytes: &[u8]) -> Option<UserEvent> {
+		if bytes.len() < 16 || bytes.len() > 16 + MAX_CONTENT_SIZE {
 			return None;
 		}
 
 		// let id1 = packet.read_i32(0);
 		// let id2 = packet.read_i32(4);
-		let opcode = msg_header.read_u32(8);
-		let size = msg_header.read_u32(12);
-
-		// Would be a bad idea to dynamically allocate a number of bytes that could be u32::MAX.
-		if size > 1024 {
-			self.connected = false;
-			return None;
-		}
+		let opcode = bytes.read_u32(8);
 
+		// Receivers expect the content padded with zeroes.
 		let mut packet = [0; 1024];
-		let n = self.read(&mut packet[..size as usize])?;
-		if n < size as usize {
-			return None;
-		}
+		packet[..bytes.len() - 16].copy_from_slice(&bytes[16..]);
 
 		let event = match opcode {
 			0 => self.cmsg_new_user(&packet),
@@ -259,20 +425,16 @@ impl User {
 		Some(event)
 	}
 
-	fn position_update(&mut self) -> Option<UserEvent> {
-		let mut packet: [u8; 26] = [0; 26];
-		let n = self.read(&mut packet)?;
-		if n < 26 {
+	fn position_update(&mut self, packet: &[u8]) -> Option<UserEvent> {
+		if packet.len() < 26 {
 			return None;
 		}
 
-		self.position.set(
+		Some(UserEvent::PositionUpdate(Vector3::new(
 			packet.read_f32(12),
 			packet.read_f32(16),
 			packet.read_f32(20),
-		);
-
-		Some(UserEvent::PositionUpdate(self.position.clone()))
+		)))
 	}
 
 	/* General Message Receivers */
@@ -283,42 +445,7 @@ impl User {
 			return None;
 		}
 
-		let avatar = validate_avatar(packet.read_string(username.len() + 1));
-
-		self.username.clone_from(&username);
-		self.avatar.clone_from(&avatar);
-
-		self.send(
-			&ByteWriter::general_message(0, self.id, Opcode::SMsgClientId, &self.id.to_be_bytes())
-				.bytes,
-		);
-
-		self.send(
-			&ByteWriter::general_message(
-				self.id,
-				self.id,
-				Opcode::SMsgUserJoined,
-				&ByteWriter::new(8)
-					.write_i32(self.id)
-					.write_i32(self.id)
-					.write_string(&self.avatar)
-					.write_string(&self.username)
-					.bytes,
-			)
-			.bytes,
-		);
-
-		self.send(
-			&ByteWriter::general_message(
-				self.id,
-				self.id,
-				Opcode::SMsgBroadcastId,
-				&self.id.to_be_bytes(),
-			)
-			.bytes,
-		);
-
-		self.initialized = true; // ready for aura updates
+		let avatar = packet.read_string(username.len() + 1);
 
 		Some(UserEvent::NewUser(username, avatar))
 	}
@@ -364,18 +491,14 @@ impl User {
 		for i in 0..9 {
 			mat.data[i] = content.read_f32(i * 4);
 		}
-		self.rotation = mat;
 
-		self.position = Vector3::new(
+		let pos = Vector3::new(
 			content.read_f32(36),
 			content.read_f32(40),
 			content.read_f32(44),
 		);
 
-		Some(UserEvent::TransformUpdate(
-			self.rotation.clone(),
-			self.position.clone(),
-		))
+		Some(UserEvent::TransformUpdate(mat, pos))
 	}
 
 	fn chat_send(&self, content: &[u8]) -> Option<UserEvent> {
@@ -398,17 +521,11 @@ impl User {
 	}
 
 	fn name_change(&mut self, content: &[u8]) -> Option<UserEvent> {
-		let name = content.read_string(0);
-		self.username.clone_from(&name);
-
-		Some(UserEvent::NameChange(name))
+		Some(UserEvent::NameChange(content.read_string(0)))
 	}
 
 	fn avatar_change(&mut self, content: &[u8]) -> Option<UserEvent> {
-		let avatar = validate_avatar(content.read_string(0));
-		self.avatar.clone_from(&avatar);
-
-		Some(UserEvent::AvatarChange(avatar))
+		Some(UserEvent::AvatarChange(content.read_string(0)))
 	}
 
 	fn private_chat(&mut self, id: i32, content: &[u8]) -> Option<UserEvent> {
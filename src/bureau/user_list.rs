@@ -2,6 +2,7 @@ use std::{
 	io::{self, Write},
 	net::TcpStream,
 	rc::Rc,
+	time::{Duration, Instant},
 };
 
 #[cfg(not(debug_assertions))]
@@ -10,9 +11,11 @@ use std::cell::UnsafeCell;
 #[cfg(debug_assertions)]
 use std::cell::{Ref, RefCell, RefMut};
 
-use hashbrown::HashMap;
+use hashbrown::{HashMap, HashSet};
 
 use super::{
+	fanout::{Fanout, Movement, UpdateRate},
+	grid::Grid,
 	protocol::{ByteWriter, Opcode},
 	user::User,
 };
@@ -24,6 +27,40 @@ pub struct UserList {
 
 	max_index: i32,
 	user_index: i32,
+
+	/// Include bots in the user count sent to clients.
+	count_bots: bool,
+	/// Bots moved by plugins, their aura is updated on the next poll.
+	moved_bots: HashSet<i32>,
+	/// Pairs of users plugins forced in or out of each other's aura, see `pair`.
+	aura_overrides: HashMap<(i32, i32), bool>,
+	/// Users whose aura settings were changed by plugins, their aura is updated on the next poll.
+	stale_auras: HashSet<i32>,
+	/// Users with their own aura radius, they can reach users outside of a grid query.
+	custom_radius: HashSet<i32>,
+	/// Where users were the last time their aura was updated.
+	grid: Grid,
+	/// When pairs of users last entered or left each other's aura, kept for the dwell time.
+	aura_changed_at: HashMap<(i32, i32), Instant>,
+	/// Aura changes left this tick, `None` if there's no limit.
+	aura_budget: Option<usize>,
+	/// Movement waiting to be forwarded when the update rate is limited.
+	fanout: Fanout,
+}
+
+/// How auras are updated, set from the bureau's config.
+#[derive(Clone, PartialEq)]
+pub struct AuraSettings {
+	pub radius: f32,
+	/// Extra distance past the radius users have to move before leaving an aura.
+	pub leave_margin: f32,
+	/// Min time between a pair of users entering and leaving each other's aura.
+	pub dwell: Duration,
+}
+
+/// Key of a pair of users in `aura_overrides` and `aura_changed_at`, the same for both orders.
+fn pair(a: i32, b: i32) -> (i32, i32) {
+	(a.min(b), a.max(b))
 }
 
 impl UserList {
@@ -34,7 +71,42 @@ impl UserList {
 
 			max_index: max_users,
 			user_index: 0,
+
+			count_bots: false,
+			moved_bots: HashSet::new(),
+			aura_overrides: HashMap::new(),
+			stale_auras: HashSet::new(),
+			custom_radius: HashSet::new(),
+			grid: Grid::new(0.0),
+			aura_changed_at: HashMap::new(),
+			aura_budget: None,
+			fanout: Fanout::default(),
+		}
+	}
+
+	/// Change the max number of users, users over the new limit are kept.
+	pub fn set_max_users(&mut self, max_users: i32) {
+		self.max_index = max_users;
+	}
+
+	pub fn set_count_bots(&mut self, count_bots: bool) {
+		self.count_bots = count_bots;
+	}
+
+	/// Rebuild the grid with a new cell size, the aura radius makes a good size.
+	pub fn set_grid_size(&mut self, cell_size: f32) {
+		let mut grid = Grid::new(cell_size);
+		if grid.cell_size() == self.grid.cell_size() {
+			return;
+		}
+
+		for user in self.users.values() {
+			if self.grid.contains(user.id()) {
+				grid.insert(user.id(), user.pos());
+			}
 		}
+
+		self.grid = grid;
 	}
 
 	fn next_id(&mut self) -> Option<i32> {
@@ -79,12 +151,190 @@ impl UserList {
 		Ok(true)
 	}
 
+	/// Add a bot, returns its id or `None` if the bureau is full.
+	pub fn add_bot(&mut self, username: String, avatar: String, data: String) -> Option<i32> {
+		let id = self.next_id()?;
+		self.users
+			.insert(id, User::new_bot(id, username, avatar, data));
+		self.moved_bots.insert(id);
+
+		Some(id)
+	}
+
+	/// Mark a bot as moved so its aura gets updated.
+	pub fn bot_moved(&mut self, id: i32) {
+		self.moved_bots.insert(id);
+	}
+
+	/// Take every bot that moved since the last call.
+	pub fn take_moved_bots(&mut self) -> Vec<i32> {
+		self.moved_bots.drain().collect()
+	}
+
+	/// Force `a` and `b` in (`true`) or out (`false`) of each other's aura, `None` removes the
+	/// override.
+	pub fn set_aura_override(&mut self, a: i32, b: i32, include: Option<bool>) {
+		match include {
+			Some(include) => self.aura_overrides.insert(pair(a, b), include),
+			None => self.aura_overrides.remove(&pair(a, b)),
+		};
+
+		self.stale_auras.insert(a);
+	}
+
+	pub fn aura_override(&self, a: i32, b: i32) -> Option<bool> {
+		self.aura_overrides.get(&pair(a, b)).copied()
+	}
+
+	/// Give `id` their own aura radius, `None` uses the config's again.
+	pub fn set_aura_radius(&mut self, id: i32, radius: Option<f32>) {
+		let Some(user) = self.users.get_mut(&id) else {
+			return;
+		};
+
+		user.set_aura_radius(radius);
+		if radius.is_some() {
+			self.custom_radius.insert(id);
+		} else {
+			self.custom_radius.remove(&id);
+		}
+
+		self.stale_auras.insert(id);
+	}
+
+	/// Mark the aura of `id` as stale so it gets updated on the next poll.
+	pub fn aura_changed(&mut self, id: i32) {
+		self.stale_auras.insert(id);
+	}
+
+	/// Take every user whose aura went stale since the last call.
+	pub fn take_stale_auras(&mut self) -> Vec<i32> {
+		self.stale_auras.drain().collect()
+	}
+
+	/// Start a new tick, allowing `max_changes` more aura changes (`None` for no limit) and
+	/// forgetting pair changes older than `dwell`.
+	pub fn new_aura_tick(&mut self, max_changes: Option<usize>, dwell: Duration) {
+		self.aura_budget = max_changes;
+		self.aura_changed_at.retain(|_, at| at.elapsed() < dwell);
+	}
+
+	/// Add users that should be in the aura of `id` and remove the ones that shouldn't.
+	///
+	/// `settings.radius` is used for users without a radius of their own, a pair uses the larger
+	/// radius of the two. Only users that could be in the aura are checked: users near `id` in
+	/// the grid, users already in the aura, users with their own radius and overridden pairs.
+	///
+	/// Changes that have to wait for the dwell time or the next tick's budget leave the aura of
+	/// `id` stale so they're tried again on the next poll.
+	pub fn update_aura(&mut self, id: i32, settings: &AuraSettings) {
+		let Some(mut user) = self.users.remove(&id) else {
+			return;
+		};
+
+		self.grid.insert(id, user.pos());
+
+		let radius = settings.radius;
+		let mut candidates = self
+			.grid
+			.query(user.pos(), user.aura_radius().unwrap_or(radius).max(radius))
+			.into_iter()
+			.collect::<HashSet<_>>();
+		candidates.extend(user.aura());
+		candidates.extend(&self.custom_radius);
+		candidates.extend(self.aura_overrides.keys().filter_map(|(a, b)| {
+			if *a == id {
+				Some(*b)
+			} else if *b == id {
+				Some(*a)
+			} else {
+				None
+			}
+		}));
+
+		let mut deferred = false;
+
+		for other_id in candidates {
+			let Some(other) = self.users.get_mut(&other_id) else {
+				continue;
+			};
+
+			if !other.initialized() {
+				continue;
+			}
+
+			let key = pair(id, other_id);
+			let in_aura = user.aura().contains(&other_id);
+
+			// Overrides and hidden users apply right away, distance changes respect the leave
+			// margin and dwell time.
+			let should_be_in_aura = match self.aura_overrides.get(&key) {
+				Some(include) => *include,
+				None if user.hidden() || other.hidden() => false,
+				None => {
+					let mut radius = user
+						.aura_radius()
+						.unwrap_or(radius)
+						.max(other.aura_radius().unwrap_or(radius));
+					if in_aura {
+						radius += settings.leave_margin;
+					}
+
+					let in_radius = user.pos().distance_sqr(other.pos()) <= radius.powi(2);
+					let dwelling = self
+						.aura_changed_at
+						.get(&key)
+						.is_some_and(|at| at.elapsed() < settings.dwell);
+
+					if in_radius != in_aura && dwelling {
+						deferred = true;
+						continue;
+					}
+
+					in_radius
+				}
+			};
+
+			if should_be_in_aura == in_aura {
+				continue;
+			}
+
+			match &mut self.aura_budget {
+				Some(0) => {
+					deferred = true;
+					continue;
+				}
+				Some(budget) => *budget -= 1,
+				None => (),
+			}
+
+			if !settings.dwell.is_zero() {
+				self.aura_changed_at.insert(key, Instant::now());
+			}
+
+			if should_be_in_aura {
+				user.add_aura(other);
+				other.add_aura(&user);
+			} else {
+				user.remove_aura(other);
+				other.remove_aura(&user);
+			}
+		}
+
+		self.users.insert(id, user);
+
+		if deferred {
+			self.stale_auras.insert(id);
+		}
+	}
+
 	pub fn master(&mut self) -> Option<i32> {
-		if self.users.contains_key(&self.master_id) {
-			return Some(self.master_id);
+		if let Some(master_id) = self.master_id() {
+			return Some(master_id);
 		}
 
-		if let Some((_, user)) = self.users.iter_mut().next() {
+		// Bots can't answer requests sent to the master.
+		if let Some(user) = self.users.values_mut().find(|user| !user.is_bot()) {
 			self.master_id = user.id();
 
 			user.send(
@@ -98,9 +348,30 @@ impl UserList {
 		None
 	}
 
+	/// Current master without choosing a new one, `None` if the master left or there is none.
+	pub fn master_id(&self) -> Option<i32> {
+		self.users
+			.get(&self.master_id)
+			.is_some_and(|user| !user.is_bot())
+			.then_some(self.master_id)
+	}
+
+	/// Check if `id` is the current master without choosing a new one.
+	pub fn is_master(&self, id: i32) -> bool {
+		self.master_id == id
+	}
+
 	pub fn disconnect(&mut self, id: i32) {
 		self.for_aura(id, |user, other| other.remove_aura(user));
 		self.users.remove(&id);
+		self.moved_bots.remove(&id);
+		self.stale_auras.remove(&id);
+		self.custom_radius.remove(&id);
+		self.grid.remove(id);
+		self.aura_overrides.retain(|(a, b), _| *a != id && *b != id);
+		self.aura_changed_at
+			.retain(|(a, b), _| *a != id && *b != id);
+		self.fanout.remove(id);
 	}
 
 	/// Iterate over all Users in the UserList while keeping a mutable reference to `id`.
@@ -132,9 +403,18 @@ impl UserList {
 		self.users.insert(id, user);
 	}
 
+	/// Number of connected Users, bots are only counted if `count_bots` is set.
+	pub fn user_count(&self) -> usize {
+		if self.count_bots {
+			return self.users.len();
+		}
+
+		self.users.values().filter(|user| !user.is_bot()).count()
+	}
+
 	/// Broadcast the current number of connected Users to all Users.
 	pub fn send_user_count(&mut self) {
-		let count = self.users.len();
+		let count = self.user_count();
 		let msg = ByteWriter::general_message(
 			0,
 			0,
@@ -159,6 +439,39 @@ impl UserList {
 		});
 	}
 
+	/// Forward a movement packet from `id` to their aura, limited to `rate`.
+	///
+	/// Aura members that got an update too recently are sent the latest packet of each kind
+	/// once their interval passes, by `flush_movement`.
+	pub fn send_movement(&mut self, id: i32, kind: Movement, bytes: Vec<u8>, rate: &UpdateRate) {
+		if !rate.is_limited() {
+			self.send_aura(id, &bytes);
+			return;
+		}
+
+		let Some(user) = self.users.remove(&id) else {
+			return;
+		};
+
+		self.fanout.push(&user, kind, bytes);
+		self.fanout.flush(&user, &mut self.users, rate);
+
+		self.users.insert(id, user);
+	}
+
+	/// Send movement held back by `send_movement` to users whose interval passed.
+	pub fn flush_movement(&mut self, rate: &UpdateRate) {
+		for id in self.fanout.pending_senders() {
+			let Some(user) = self.users.remove(&id) else {
+				continue;
+			};
+
+			self.fanout.flush(&user, &mut self.users, rate);
+
+			self.users.insert(id, user);
+		}
+	}
+
 	pub fn send_aura(&mut self, id: i32, buf: &[u8]) {
 		self.for_aura(id, |_, other| {
 			other.send(buf);
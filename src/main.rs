@@ -1,7 +1,12 @@
 use clap::{Args, Parser, Subcommand};
-use std::net::{IpAddr, SocketAddr};
-
-use bureau::{Bureau, BureauConfig};
+use std::{
+	net::{IpAddr, SocketAddr},
+	path::PathBuf,
+};
+
+use bureau::{
+	Bounds, Bureau, BureauConfig, MovementRules, NameCharset, NameRules, SandboxConfig, Storage,
+};
 use wls::WlsOptions;
 
 mod bureau;
@@ -38,6 +43,34 @@ struct BureauArgs {
 	#[arg(short, long, default_value_t = 200.0)]
 	aura_radius: f32,
 
+	/// Distance users have to move away before leaving an aura, the aura radius when unset
+	#[arg(long, value_parser = non_negative)]
+	aura_leave_radius: Option<f32>,
+
+	/// Milliseconds two users stay in or out of each other's aura before it can change again
+	#[arg(long, default_value_t = 0)]
+	aura_dwell: u64,
+
+	/// Max aura changes per tick, changes over the limit are made on later ticks (0 for no limit)
+	#[arg(long, default_value_t = 0)]
+	max_aura_changes: usize,
+
+	/// Max movement updates per second forwarded from one user to another (0 for no limit)
+	#[arg(long, default_value_t = 0.0, value_parser = non_negative)]
+	max_update_rate: f32,
+
+	/// Users further away than this get movement updates less often (0 to disable)
+	#[arg(long, default_value_t = 0.0, value_parser = non_negative)]
+	update_falloff: f32,
+
+	/// Box users have to stay inside of, as two corners x1,y1,z1,x2,y2,z2
+	#[arg(long, allow_hyphen_values = true)]
+	world_bounds: Option<Bounds>,
+
+	/// Max distance per second users can move between updates
+	#[arg(long, value_parser = non_negative)]
+	max_speed: Option<f32>,
+
 	/// Amount of time to wait before disconnecting connecting users
 	#[arg(long, default_value_t = 10)]
 	connect_timeout: u64,
@@ -49,6 +82,62 @@ struct BureauArgs {
 	/// Max number of incoming connections to allow
 	#[arg(long, default_value_t = 10)]
 	max_queue: usize,
+
+	/// Only load these plugins (comma separated), all plugins are loaded when unset
+	#[arg(long, value_delimiter = ',')]
+	plugins: Option<Vec<String>>,
+
+	/// Load plugins with a restricted standard library and resource limits
+	#[arg(long)]
+	sandbox: bool,
+
+	/// Max Lua instructions per hook call when sandboxed
+	#[arg(long, default_value_t = 1_000_000)]
+	sandbox_instructions: u32,
+
+	/// Max memory in KiB a hook call can allocate when sandboxed
+	#[arg(long, default_value_t = 16 * 1024)]
+	sandbox_memory: usize,
+
+	/// Message sent to users when they join
+	#[arg(long)]
+	motd: Option<String>,
+
+	/// Allowed avatars, either a file with one avatar per line or a directory of avatar files
+	#[arg(long)]
+	avatars: Option<PathBuf>,
+
+	/// Avatar given to users that pick one that isn't allowed
+	#[arg(long, default_value = "avtwrl/01cat.wrl")]
+	default_avatar: String,
+
+	/// Min username length
+	#[arg(long, default_value_t = 1)]
+	name_min_length: usize,
+
+	/// Max username length
+	#[arg(long, default_value_t = 32)]
+	name_max_length: usize,
+
+	/// Characters allowed in usernames (printable, ascii or alphanumeric)
+	#[arg(long, default_value = "printable")]
+	name_charset: NameCharset,
+
+	/// Let users share a name instead of numbering duplicates
+	#[arg(long)]
+	allow_duplicate_names: bool,
+
+	/// Names only plugins can give out (comma separated)
+	#[arg(long, value_delimiter = ',')]
+	reserved_names: Vec<String>,
+
+	/// Include bots spawned by plugins in the user count sent to clients
+	#[arg(long)]
+	count_bots: bool,
+
+	/// Directory plugin storage is saved in
+	#[arg(long, default_value = "storage")]
+	storage: PathBuf,
 }
 
 #[derive(Args)]
@@ -61,11 +150,20 @@ struct WlsArgs {
 	#[arg(short, long, default_value_t = 2)]
 	max_bureaus: usize,
 
-	/// Max players per bureau
+	/// File listing wrls to host, each optionally followed by indented `key = value` settings
 	#[arg(long)]
 	wrl_list: Option<String>,
 }
 
+/// Parse a finite number that isn't negative.
+fn non_negative(s: &str) -> Result<f32, String> {
+	match s.parse::<f32>() {
+		Ok(n) if n.is_finite() && n >= 0.0 => Ok(n),
+		Ok(_) => Err("must be a finite number that isn't negative".to_string()),
+		Err(e) => Err(e.to_string()),
+	}
+}
+
 fn main() {
 	let cli = Cli::parse();
 
@@ -76,6 +174,32 @@ fn main() {
 		max_users: cli.bureau.max_users,
 		max_queue: cli.bureau.max_queue,
 		aura_radius: cli.bureau.aura_radius,
+		aura_leave_radius: cli.bureau.aura_leave_radius,
+		aura_dwell: cli.bureau.aura_dwell,
+		max_aura_changes: cli.bureau.max_aura_changes,
+		max_update_rate: cli.bureau.max_update_rate,
+		update_falloff: cli.bureau.update_falloff,
+		plugins: cli.bureau.plugins,
+		sandbox: cli.bureau.sandbox.then(|| SandboxConfig {
+			instruction_limit: cli.bureau.sandbox_instructions,
+			memory_limit: cli.bureau.sandbox_memory * 1024,
+		}),
+		motd: cli.bureau.motd,
+		avatars: cli.bureau.avatars,
+		default_avatar: cli.bureau.default_avatar,
+		names: NameRules {
+			min_length: cli.bureau.name_min_length,
+			max_length: cli.bureau.name_max_length,
+			charset: cli.bureau.name_charset,
+			unique: !cli.bureau.allow_duplicate_names,
+			reserved: cli.bureau.reserved_names,
+		},
+		movement: MovementRules {
+			bounds: cli.bureau.world_bounds,
+			max_speed: cli.bureau.max_speed,
+		},
+		count_bots: cli.bureau.count_bots,
+		storage: Storage::new(cli.bureau.storage),
 		wrl: None,
 	};
 
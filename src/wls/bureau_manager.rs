@@ -29,6 +29,44 @@ impl BureauManager {
 		}
 	}
 
+	pub fn wrl(&self) -> &str {
+		&self.wrl
+	}
+
+	/// Whether this manager has no running bureaus.
+	pub fn is_empty(&self) -> bool {
+		self.bureaus.is_empty()
+	}
+
+	/// Apply new settings to this manager and all of its running bureaus.
+	pub fn reconfigure(&mut self, max: usize, bureau_config: BureauConfig) {
+		if self.bureaus.len() > max {
+			println!(
+				"'{}' has {} bureaus running, over the new max of {}. No new bureaus will be started until some close.",
+				self.wrl,
+				self.bureaus.len(),
+				max
+			);
+		}
+
+		self.max = max;
+
+		for bureau_ex in self.bureaus.iter_mut() {
+			let mut config = bureau_config.clone();
+			config.wrl = Some(self.wrl.clone());
+
+			bureau_ex.inner.reconfigure(config);
+		}
+
+		self.bureau_config = bureau_config;
+	}
+
+	pub fn reload_plugins(&mut self) {
+		for bureau_ex in self.bureaus.iter_mut() {
+			bureau_ex.inner.reload_plugins();
+		}
+	}
+
 	pub fn poll(&mut self) {
 		self.bureaus.retain_mut(|bureau_ex| {
 			if let Err(err) = bureau_ex.inner.poll() {
@@ -40,9 +78,11 @@ impl BureauManager {
 	}
 
 	pub fn available(&mut self) -> Option<u16> {
-		if let Some(bureau_ex) = self.bureaus.iter().find(|bureau_ex| {
-			bureau_ex.inner.user_count() < bureau_ex.inner.config().max_users as usize
-		}) {
+		if let Some(bureau_ex) = self
+			.bureaus
+			.iter()
+			.find(|bureau_ex| bureau_ex.inner.has_room())
+		{
 			return Some(bureau_ex.inner.port());
 		}
 
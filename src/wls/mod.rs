@@ -1,15 +1,22 @@
 mod bureau_manager;
+mod wrl_list;
 
 use std::{
 	fs::File,
-	io::{self, BufRead, BufReader, ErrorKind, Read, Write},
+	io::{self, BufReader, ErrorKind, Read, Write},
 	net::{TcpListener, ToSocketAddrs},
+	sync::{
+		Arc,
+		atomic::{AtomicBool, Ordering},
+	},
 	thread,
 	time::{Duration, Instant},
 };
 
 use bureau_manager::BureauManager;
 use hashbrown::HashMap;
+use signal_hook::consts::{SIGHUP, SIGUSR1};
+use wrl_list::{WrlEntry, WrlSettings};
 
 use crate::bureau::BureauConfig;
 
@@ -20,16 +27,86 @@ pub struct WlsOptions {
 	pub bureau_config: BureauConfig,
 }
 
-fn default_wrls() -> Vec<String> {
-	vec![
-		"SAPARi COAST MIL.".into(),
-		"SAPARi DOWNTOWN MIL.".into(),
-		"HONJO JIDAIMURA MIL.".into(),
-		"SAPARi PARK MIL.".into(),
-		"SAPARi SPA".into(),
-		"SAPARi GARDEN".into(),
-		"SAPARi HILLS".into(),
+fn default_wrls() -> Vec<WrlEntry> {
+	[
+		"SAPARi COAST MIL.",
+		"SAPARi DOWNTOWN MIL.",
+		"HONJO JIDAIMURA MIL.",
+		"SAPARi PARK MIL.",
+		"SAPARi SPA",
+		"SAPARi GARDEN",
+		"SAPARi HILLS",
 	]
+	.into_iter()
+	.map(|name| WrlEntry {
+		name: name.into(),
+		settings: WrlSettings::default(),
+	})
+	.collect()
+}
+
+fn read_wrls(options: &WlsOptions) -> io::Result<Vec<WrlEntry>> {
+	// Check wrl_list and read file if it's set
+	match &options.wrl_list {
+		Some(path) => wrl_list::parse(BufReader::new(File::open(path)?)),
+		None => Ok(default_wrls()),
+	}
+}
+
+/// Get the max bureau count and bureau config for a wrl.
+fn wrl_config(options: &WlsOptions, settings: &WrlSettings) -> (usize, BureauConfig) {
+	let mut bureau_config = options.bureau_config.clone();
+	settings.apply(&mut bureau_config);
+
+	(
+		settings.max_bureaus.unwrap_or(options.max_bureaus),
+		bureau_config,
+	)
+}
+
+/// Re-read the wrl list and apply it to the running managers.
+fn reload(
+	options: &WlsOptions,
+	managers: &mut HashMap<String, BureauManager>,
+	retired: &mut Vec<BureauManager>,
+) -> io::Result<()> {
+	let wrls = read_wrls(options)?;
+
+	for WrlEntry { name, settings } in wrls.iter() {
+		let (max_bureaus, bureau_config) = wrl_config(options, settings);
+
+		match managers.get_mut(name) {
+			Some(manager) => manager.reconfigure(max_bureaus, bureau_config),
+			None => {
+				println!("Adding wrl '{}'.", name);
+				managers.insert(
+					name.clone(),
+					BureauManager::new(name.clone(), max_bureaus, bureau_config),
+				);
+			}
+		}
+	}
+
+	let removed = managers
+		.keys()
+		.filter(|name| !wrls.iter().any(|entry| &entry.name == *name))
+		.cloned()
+		.collect::<Vec<_>>();
+
+	for name in removed {
+		let manager = managers.remove(&name).unwrap();
+		if !manager.is_empty() {
+			println!(
+				"Removed wrl '{}' still has running bureaus, they will close once empty.",
+				name
+			);
+			retired.push(manager);
+		} else {
+			println!("Removing wrl '{}'.", name);
+		}
+	}
+
+	Ok(())
 }
 
 pub fn run<A: ToSocketAddrs>(addr: A, options: WlsOptions) -> io::Result<()> {
@@ -37,27 +114,44 @@ pub fn run<A: ToSocketAddrs>(addr: A, options: WlsOptions) -> io::Result<()> {
 	listener.set_nonblocking(true)?;
 	let wls_port = listener.local_addr()?.port();
 
-	// Check wrl_list and read file if it's set
-	let wrls = match &options.wrl_list {
-		Some(path) => {
-			let reader = BufReader::new(File::open(path)?);
-			reader.lines().map_while(Result::ok).collect()
-		}
-		None => default_wrls(),
-	};
+	let wrls = read_wrls(&options)?;
 
 	let mut managers = HashMap::with_capacity(wrls.len());
-	for wrl in wrls {
+	for WrlEntry { name, settings } in wrls {
+		let (max_bureaus, bureau_config) = wrl_config(&options, &settings);
 		managers.insert(
-			wrl.clone(),
-			BureauManager::new(wrl, options.max_bureaus, options.bureau_config.clone()),
+			name.clone(),
+			BureauManager::new(name, max_bureaus, bureau_config),
 		);
 	}
 
+	// Managers for wrls that were removed from the list while they still had users.
+	let mut retired: Vec<BureauManager> = Vec::new();
+
+	let reload_requested = Arc::new(AtomicBool::new(false));
+	signal_hook::flag::register(SIGHUP, reload_requested.clone())?;
+
+	let plugin_reload_requested = Arc::new(AtomicBool::new(false));
+	signal_hook::flag::register(SIGUSR1, plugin_reload_requested.clone())?;
+
 	let mut queue = Vec::new();
 
 	println!("WLS running on port: {}.", wls_port);
 	loop {
+		if reload_requested.swap(false, Ordering::Relaxed) {
+			println!("Reloading wrl list.");
+			if let Err(e) = reload(&options, &mut managers, &mut retired) {
+				eprintln!("Failed to reload wrl list, keeping current config. {}", e);
+			}
+		}
+
+		if plugin_reload_requested.swap(false, Ordering::Relaxed) {
+			println!("Reloading plugins.");
+			for manager in managers.values_mut().chain(retired.iter_mut()) {
+				manager.reload_plugins();
+			}
+		}
+
 		if let Ok((socket, _)) = listener.accept()
 			&& let Ok(()) = socket.set_nonblocking(true)
 		{
@@ -109,6 +203,17 @@ pub fn run<A: ToSocketAddrs>(addr: A, options: WlsOptions) -> io::Result<()> {
 			manager.poll();
 		}
 
+		retired.retain_mut(|manager| {
+			manager.poll();
+
+			if manager.is_empty() {
+				println!("Removing wrl '{}'.", manager.wrl());
+				return false;
+			}
+
+			true
+		});
+
 		thread::sleep(Duration::from_millis(100));
 	}
 }
@@ -0,0 +1,244 @@
+use std::{
+	io::{self, BufRead, ErrorKind},
+	path::PathBuf,
+};
+
+use crate::bureau::{Bounds, BureauConfig};
+
+/// Per-wrl overrides, anything left as `None` falls back to the command line options.
+#[derive(Default)]
+pub struct WrlSettings {
+	pub max_bureaus: Option<usize>,
+	pub max_users: Option<i32>,
+	pub aura_radius: Option<f32>,
+	pub aura_leave_radius: Option<f32>,
+	pub aura_dwell: Option<u64>,
+	pub max_aura_changes: Option<usize>,
+	pub max_update_rate: Option<f32>,
+	pub update_falloff: Option<f32>,
+	pub world_bounds: Option<Bounds>,
+	pub max_speed: Option<f32>,
+	pub plugins: Option<Vec<String>>,
+	pub motd: Option<String>,
+	pub avatars: Option<PathBuf>,
+	pub default_avatar: Option<String>,
+	pub reserved_names: Option<Vec<String>>,
+}
+
+impl WrlSettings {
+	/// Apply these settings on top of `config`.
+	pub fn apply(&self, config: &mut BureauConfig) {
+		if let Some(max_users) = self.max_users {
+			config.max_users = max_users;
+		}
+
+		if let Some(aura_radius) = self.aura_radius {
+			config.aura_radius = aura_radius;
+		}
+
+		if let Some(aura_leave_radius) = self.aura_leave_radius {
+			config.aura_leave_radius = Some(aura_leave_radius);
+		}
+
+		if let Some(aura_dwell) = self.aura_dwell {
+			config.aura_dwell = aura_dwell;
+		}
+
+		if let Some(max_aura_changes) = self.max_aura_changes {
+			config.max_aura_changes = max_aura_changes;
+		}
+
+		if let Some(max_update_rate) = self.max_update_rate {
+			config.max_update_rate = max_update_rate;
+		}
+
+		if let Some(update_falloff) = self.update_falloff {
+			config.update_falloff = update_falloff;
+		}
+
+		if let Some(world_bounds) = &self.world_bounds {
+			config.movement.bounds = Some(world_bounds.clone());
+		}
+
+		if let Some(max_speed) = self.max_speed {
+			config.movement.max_speed = Some(max_speed);
+		}
+
+		if let Some(plugins) = &self.plugins {
+			config.plugins = Some(plugins.clone());
+		}
+
+		if let Some(motd) = &self.motd {
+			config.motd = Some(motd.clone());
+		}
+
+		if let Some(avatars) = &self.avatars {
+			config.avatars = Some(avatars.clone());
+		}
+
+		if let Some(default_avatar) = &self.default_avatar {
+			config.default_avatar.clone_from(default_avatar);
+		}
+
+		if let Some(reserved_names) = &self.reserved_names {
+			config.names.reserved.clone_from(reserved_names);
+		}
+	}
+
+	fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+		fn parse<T: std::str::FromStr>(key: &str, value: &str) -> Result<T, String> {
+			value
+				.parse()
+				.map_err(|_| format!("invalid value '{}' for '{}'", value, key))
+		}
+
+		fn non_negative(key: &str, value: &str) -> Result<f32, String> {
+			let n: f32 = parse(key, value)?;
+			if !n.is_finite() || n < 0.0 {
+				return Err(format!("'{}' can't be negative ({})", key, value));
+			}
+
+			Ok(n)
+		}
+
+		match key {
+			"max_bureaus" => self.max_bureaus = Some(parse(key, value)?),
+			"max_users" => {
+				let max_users = parse(key, value)?;
+				if max_users <= 0 {
+					return Err(format!("'max_users' must be positive ({})", max_users));
+				}
+
+				self.max_users = Some(max_users);
+			}
+			"aura_radius" => self.aura_radius = Some(parse(key, value)?),
+			"aura_leave_radius" => self.aura_leave_radius = Some(non_negative(key, value)?),
+			"aura_dwell" => self.aura_dwell = Some(parse(key, value)?),
+			"max_aura_changes" => self.max_aura_changes = Some(parse(key, value)?),
+			"max_update_rate" => self.max_update_rate = Some(non_negative(key, value)?),
+			"update_falloff" => self.update_falloff = Some(non_negative(key, value)?),
+			"world_bounds" => self.world_bounds = Some(parse(key, value)?),
+			"max_speed" => self.max_speed = Some(non_negative(key, value)?),
+			"plugins" => self.plugins = Some(list(value)),
+			"motd" => self.motd = Some(value.to_string()),
+			"avatars" => self.avatars = Some(value.into()),
+			"default_avatar" => self.default_avatar = Some(value.to_string()),
+			"reserved_names" => self.reserved_names = Some(list(value)),
+			_ => return Err(format!("unknown setting '{}'", key)),
+		}
+
+		Ok(())
+	}
+}
+
+/// Split a comma separated list.
+fn list(value: &str) -> Vec<String> {
+	value
+		.split(',')
+		.map(str::trim)
+		.filter(|s| !s.is_empty())
+		.map(String::from)
+		.collect()
+}
+
+pub struct WrlEntry {
+	pub name: String,
+	pub settings: WrlSettings,
+}
+
+/// Parse a wrl list.
+///
+/// Every unindented line is the name of a wrl, indented `key = value` lines after it are
+/// settings for that wrl.
+///
+/// ```text
+/// SAPARi COAST MIL.
+///     max_users = 100
+///     plugins = greeter, chat-log
+/// SAPARi SPA
+/// ```
+pub fn parse<R: BufRead>(reader: R) -> io::Result<Vec<WrlEntry>> {
+	let mut entries: Vec<WrlEntry> = Vec::new();
+
+	for (i, line) in reader.lines().enumerate() {
+		let line = line?;
+		let invalid = |msg: String| {
+			io::Error::new(ErrorKind::InvalidData, format!("line {}: {}", i + 1, msg))
+		};
+
+		if line.trim().is_empty() {
+			continue;
+		}
+
+		if !line.starts_with(char::is_whitespace) {
+			if entries.iter().any(|entry| entry.name == line) {
+				return Err(invalid(format!("duplicate wrl '{}'", line)));
+			}
+
+			entries.push(WrlEntry {
+				name: line,
+				settings: WrlSettings::default(),
+			});
+			continue;
+		}
+
+		let Some(entry) = entries.last_mut() else {
+			return Err(invalid("setting without a wrl".into()));
+		};
+
+		let Some((key, value)) = line.split_once('=') else {
+			return Err(invalid(format!(
+				"expected 'key = value', got '{}'",
+				line.trim()
+			)));
+		};
+
+		entry
+			.settings
+			.set(key.trim(), value.trim())
+			.map_err(invalid)?;
+	}
+
+	Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn duplicate_wrls_are_rejected() {
+		let list = "a\n\tmax_users = 10\nb\na\n";
+		let err = parse(list.as_bytes()).err().unwrap();
+
+		assert_eq!(err.kind(), ErrorKind::InvalidData);
+		assert_eq!(err.to_string(), "line 4: duplicate wrl 'a'");
+	}
+
+	#[test]
+	fn settings_belong_to_the_wrl_above_them() {
+		let entries = parse("a\n\tmax_users = 10\n\nb\n".as_bytes()).unwrap();
+
+		assert_eq!(entries.len(), 2);
+		assert_eq!(entries[0].settings.max_users, Some(10));
+		assert_eq!(entries[1].settings.max_users, None);
+	}
+
+	#[test]
+	fn negative_and_non_finite_values_are_rejected() {
+		for key in [
+			"aura_leave_radius",
+			"max_update_rate",
+			"update_falloff",
+			"max_speed",
+		] {
+			let mut settings = WrlSettings::default();
+			assert!(settings.set(key, "0").is_ok(), "{}", key);
+
+			for value in ["-1", "NaN", "inf"] {
+				let err = settings.set(key, value).err().unwrap();
+				assert_eq!(err, format!("'{}' can't be negative ({})", key, value));
+			}
+		}
+	}
+}